@@ -0,0 +1,536 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, Error, LitStr, Result, Token};
+
+const EXTENSIONS: &[&str] = &["js", "mjs", "css"];
+
+/// Binary asset extensions and their MIME types
+const ASSETS: &[(&str, &str)] = &[
+    ("woff2", "font/woff2"),
+    ("woff", "font/woff"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+    ("ico", "image/x-icon"),
+];
+
+struct Paths {
+    paths: Punctuated<LitStr, Token![,]>,
+}
+
+impl Parse for Paths {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Ok(Paths {
+            paths: Punctuated::parse_terminated(input)?,
+        })
+    }
+}
+
+enum Kind {
+    Module,
+    Script,
+    Style,
+    Asset(&'static str),
+}
+
+#[derive(Debug, PartialEq)]
+enum Reference {
+    Module(Option<String>),
+    Style,
+    Export(String),
+    Import(String),
+    Url(String),
+}
+
+struct File {
+    /// path relative to the manifest directory using `/` separators
+    path: String,
+    full_path: PathBuf,
+    source: String,
+}
+
+impl File {
+    fn id(&self) -> u64 {
+        fnv1a(&self.path)
+    }
+
+    fn ident(&self) -> String {
+        self.path
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '-' })
+            .collect()
+    }
+
+    fn extension(&self) -> &str {
+        self.path.rsplit('.').next().unwrap_or("")
+    }
+
+    /// MIME type of a binary asset
+    fn mime(&self) -> Option<&'static str> {
+        mime(&self.path)
+    }
+}
+
+fn mime(path: &str) -> Option<&'static str> {
+    let extension = path.rsplit('.').next().unwrap_or("");
+    ASSETS.iter().find(|(ext, _)| *ext == extension).map(|(_, mime)| *mime)
+}
+
+pub fn include_content(input: TokenStream) -> TokenStream {
+    let paths = parse_macro_input!(input as Paths);
+    match expand(&paths) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(paths: &Paths) -> Result<TokenStream2> {
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is not set"));
+
+    let mut files = Vec::new();
+    for lit in paths.paths.iter() {
+        collect(&manifest_dir, lit, &mut files)?;
+    }
+    if files.is_empty() {
+        return Err(Error::new(proc_macro2::Span::call_site(), "no content files supplied"));
+    }
+    collect_css_assets(&manifest_dir, &mut files);
+
+    let mut ids = HashMap::new();
+    let mut paths = HashMap::new();
+    let mut idents = HashMap::new();
+    for file in files.iter() {
+        if let Some(other) = paths.insert(file.id(), &file.path) {
+            return Err(Error::new(
+                proc_macro2::Span::call_site(),
+                format!("id collision between `{}` and `{}`", other, file.path),
+            ));
+        }
+        // idents are used as DOM element ids and must be unique
+        if let Some(other) = idents.insert(file.ident(), &file.path) {
+            return Err(Error::new(
+                proc_macro2::Span::call_site(),
+                format!("ident collision between `{}` and `{}`", other, file.path),
+            ));
+        }
+        ids.insert(file.path.as_str(), file.id());
+    }
+
+    // rewrite statements referring to declared files into references
+    let parsed = files
+        .iter()
+        .map(|file| {
+            if file.mime().is_some() {
+                Ok((String::new(), Vec::new()))
+            } else if file.extension() == "css" {
+                // `@import` rules and `url()` functions are rewritten by the loader
+                Ok((file.source.clone(), parse_css(&file.path, &file.source, &ids)))
+            } else {
+                parse_module(&file.path, &file.source, &ids).map_err(|err| Error::new(proc_macro2::Span::call_site(), err))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // files imported by other files are evaluated as modules
+    let imported = parsed
+        .iter()
+        .flat_map(|(_, references)| references.iter().filter(|(reference, _)| *reference != Reference::Style).map(|(_, id)| *id))
+        .collect::<HashSet<_>>();
+
+    let mut entries = Vec::new();
+    let mut includes = Vec::new();
+    for (file, (source, references)) in files.iter().zip(parsed) {
+        let kind = match file.extension() {
+            _ if file.mime().is_some() => Kind::Asset(file.mime().unwrap()),
+            "css" => Kind::Style,
+            "mjs" => Kind::Module,
+            _ if module().is_match(&file.source) || imported.contains(&file.id()) => Kind::Module,
+            _ => Kind::Script,
+        };
+
+        let id = file.id();
+        let ident = file.ident();
+        let content_type = match kind {
+            Kind::Module => quote!(::workflow_dom::loader::ContentType::Module),
+            Kind::Script => quote!(::workflow_dom::loader::ContentType::Script),
+            Kind::Style => quote!(::workflow_dom::loader::ContentType::Style),
+            Kind::Asset(_) => quote!(::workflow_dom::loader::ContentType::Asset),
+        };
+        let references = if references.is_empty() {
+            quote!(None)
+        } else {
+            let references = references.iter().map(|(reference, id)| match reference {
+                Reference::Module(Some(what)) => quote!((::workflow_dom::loader::Reference::Module, Some(#what), #id)),
+                Reference::Module(None) => quote!((::workflow_dom::loader::Reference::Module, None, #id)),
+                Reference::Style => quote!((::workflow_dom::loader::Reference::Style, None, #id)),
+                Reference::Export(what) => quote!((::workflow_dom::loader::Reference::Export, Some(#what), #id)),
+                Reference::Import(what) => quote!((::workflow_dom::loader::Reference::Import, Some(#what), #id)),
+                Reference::Url(what) => quote!((::workflow_dom::loader::Reference::Url, Some(#what), #id)),
+            });
+            quote!(Some(&[#(#references),*]))
+        };
+
+        let full_path = file.full_path.to_string_lossy().to_string();
+        if let Kind::Asset(mime) = kind {
+            entries.push(quote! {
+                (#id, ::std::sync::Arc::new(::workflow_dom::loader::Content {
+                    content_type : #content_type,
+                    id : #id,
+                    ident : #ident,
+                    data : include_bytes!(#full_path),
+                    mime : Some(#mime),
+                    ..::core::default::Default::default()
+                }))
+            });
+            continue;
+        }
+
+        entries.push(quote! {
+            (#id, ::std::sync::Arc::new(::workflow_dom::loader::Content {
+                content_type : #content_type,
+                id : #id,
+                ident : #ident,
+                content : #source,
+                references : #references,
+                ..::core::default::Default::default()
+            }))
+        });
+
+        includes.push(quote!(include_str!(#full_path)));
+    }
+
+    Ok(quote! {
+        {
+            // track the source files so that changes trigger a rebuild
+            const _: &[&str] = &[#(#includes),*];
+            [#(#entries),*]
+        }
+    })
+}
+
+/// Matches `import` and `export ... from` statements at the start
+/// of a line or following a `;` on the same line (`lead`)
+fn statement() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r#"(?m)(?P<lead>^|;)[ \t]*(?:import\s+(?P<import>[^"';]+?)\s+from\s*|import\s*|export\s+(?P<export>\*(?:\s+as\s+[\w$]+)?|\{[^}]*\})\s*from\s*)["'](?P<spec>[^"']+)["']"#,
+        )
+        .unwrap()
+    })
+}
+
+fn module() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"(?m)^\s*(?:import|export)\b").unwrap())
+}
+
+/// Matches CSS `@import` rules (`import` or `quoted`) and `url()` functions (`url`)
+fn css() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r#"@import\s+(?:url\(\s*["']?(?P<import>[^"')\s]+)["']?\s*\)|["'](?P<quoted>[^"']+)["'])|url\(\s*["']?(?P<url>[^"')\s]+)["']?\s*\)"#,
+        )
+        .unwrap()
+    })
+}
+
+/// Remove statements of the module `source` at `path` that refer to
+/// files declared in `ids`, returning the remaining source and the references;
+/// imported stylesheets become style references and imported assets are rejected
+fn parse_module(
+    path: &str,
+    source: &str,
+    ids: &HashMap<&str, u64>,
+) -> std::result::Result<(String, Vec<(Reference, u64)>), String> {
+    let mut references = Vec::new();
+    let mut error = None;
+    let source = statement()
+        .replace_all(source, |caps: &Captures| {
+            let target = resolve(path, &caps["spec"]).and_then(|target| ids.get_key_value(target.as_str()));
+            match target {
+                Some((target, id)) => {
+                    let reference = if mime(target).is_some() {
+                        error.get_or_insert_with(|| format!("`{}`: asset `{}` can not be imported", path, target));
+                        return caps[0].to_string();
+                    } else if target.ends_with(".css") {
+                        if caps.name("export").is_some() || caps.name("import").is_some() {
+                            error.get_or_insert_with(|| format!("`{}`: stylesheet `{}` has no bindings to import", path, target));
+                            return caps[0].to_string();
+                        }
+                        Reference::Style
+                    } else if let Some(export) = caps.name("export") {
+                        Reference::Export(export.as_str().to_string())
+                    } else {
+                        Reference::Module(caps.name("import").map(|import| import.as_str().trim().to_string()))
+                    };
+                    references.push((reference, *id));
+                    // a `;` separating the statement from a preceding one is retained
+                    caps["lead"].to_string()
+                }
+                None => caps[0].to_string(),
+            }
+        })
+        .to_string();
+    match error {
+        Some(error) => Err(error),
+        None => Ok((source, references)),
+    }
+}
+
+/// Returns references made by `@import` rules and `url()` functions
+/// of the stylesheet `source` at `path` to files declared in `ids`
+fn parse_css(path: &str, source: &str, ids: &HashMap<&str, u64>) -> Vec<(Reference, u64)> {
+    let mut references = Vec::new();
+    for caps in css().captures_iter(source) {
+        let (spec, import) = match caps.name("import").or_else(|| caps.name("quoted")) {
+            Some(spec) => (spec.as_str(), true),
+            None => (&caps["url"], false),
+        };
+        let target = resolve_css(path, spec).and_then(|path| ids.get(path.as_str()).cloned());
+        if let Some(id) = target {
+            let spec = spec.to_string();
+            let reference = if import { Reference::Import(spec) } else { Reference::Url(spec) };
+            references.push((reference, id));
+        }
+    }
+    references
+}
+
+/// Collect content files for the path `lit` (a file or a directory)
+fn collect(manifest_dir: &Path, lit: &LitStr, files: &mut Vec<File>) -> Result<()> {
+    let path = normalize(&lit.value().replace('\\', "/"));
+    let full_path = manifest_dir.join(&path);
+    if full_path.is_dir() {
+        let mut entries = std::fs::read_dir(&full_path)
+            .map_err(|err| Error::new(lit.span(), format!("unable to read `{}`: {}", path, err)))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        entries.sort();
+        for entry in entries {
+            let child = manifest_dir.join(&path).join(&entry);
+            // assets are only embedded if listed explicitly or referenced by a stylesheet
+            let is_content = EXTENSIONS.iter().any(|ext| entry.ends_with(&format!(".{}", ext)));
+            if child.is_dir() || is_content {
+                let lit = LitStr::new(&format!("{}/{}", path, entry), lit.span());
+                collect(manifest_dir, &lit, files)?;
+            }
+        }
+        Ok(())
+    } else if full_path.is_file() {
+        // binary assets are embedded with `include_bytes!()`
+        let source = match mime(&path) {
+            Some(_) => String::new(),
+            None => std::fs::read_to_string(&full_path)
+                .map_err(|err| Error::new(lit.span(), format!("unable to read `{}`: {}", path, err)))?,
+        };
+        if !files.iter().any(|file| file.path == path) {
+            files.push(File { path, full_path, source });
+        }
+        Ok(())
+    } else {
+        Err(Error::new(lit.span(), format!("unable to locate `{}`", path)))
+    }
+}
+
+/// Add binary assets referenced by `url()` functions of collected
+/// stylesheets that have not been listed explicitly
+fn collect_css_assets(manifest_dir: &Path, files: &mut Vec<File>) {
+    let mut assets = Vec::new();
+    for file in files.iter().filter(|file| file.extension() == "css") {
+        for caps in css().captures_iter(&file.source) {
+            let path = caps.name("url").and_then(|spec| resolve_css(&file.path, spec.as_str()));
+            if let Some(path) = path.filter(|path| mime(path).is_some()) {
+                let full_path = manifest_dir.join(&path);
+                if full_path.is_file() && !files.iter().chain(assets.iter()).any(|file: &File| file.path == path) {
+                    assets.push(File { path, full_path, source: String::new() });
+                }
+            }
+        }
+    }
+    files.extend(assets);
+}
+
+/// Resolve a relative import specifier against the importing file path.
+/// Returns `None` for bare and absolute specifiers.
+fn resolve(from: &str, spec: &str) -> Option<String> {
+    if spec.starts_with("./") || spec.starts_with("../") {
+        let dir = from.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+        Some(normalize(&format!("{}/{}", dir, spec)))
+    } else {
+        None
+    }
+}
+
+/// Resolve a CSS `@import` or `url()` specifier against the stylesheet path.
+/// Returns `None` for absolute, fragment and scheme (e.g. `data:`) URLs.
+fn resolve_css(from: &str, spec: &str) -> Option<String> {
+    if spec.contains(':') || spec.starts_with('/') || spec.starts_with('#') {
+        return None;
+    }
+    let path = spec.split(['?', '#']).next().unwrap_or(spec);
+    let dir = from.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+    Some(normalize(&format!("{}/{}", dir, path)))
+}
+
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// 64-bit FNV-1a hash used to derive stable content ids from file paths
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids() -> HashMap<&'static str, u64> {
+        ["js/a.js", "js/b.js", "js/lib/c.js", "css/base.css", "fonts/inter.woff2"]
+            .into_iter()
+            .map(|path| (path, fnv1a(path)))
+            .collect()
+    }
+
+    #[test]
+    fn normalize_paths() {
+        assert_eq!(normalize("js/./lib/../a.js"), "js/a.js");
+        assert_eq!(normalize("/js//a.js"), "js/a.js");
+        assert_eq!(normalize("../a.js"), "a.js");
+    }
+
+    #[test]
+    fn resolve_specifiers() {
+        assert_eq!(resolve("js/a.js", "./b.js").as_deref(), Some("js/b.js"));
+        assert_eq!(resolve("js/lib/c.js", "../a.js").as_deref(), Some("js/a.js"));
+        assert_eq!(resolve("a.js", "./b.js").as_deref(), Some("b.js"));
+        assert_eq!(resolve("js/a.js", "lit"), None);
+        assert_eq!(resolve("js/a.js", "/js/b.js"), None);
+    }
+
+    #[test]
+    fn resolve_css_specifiers() {
+        assert_eq!(resolve_css("css/app.css", "base.css").as_deref(), Some("css/base.css"));
+        assert_eq!(resolve_css("css/app.css", "../fonts/inter.woff2?v=2#iefix").as_deref(), Some("fonts/inter.woff2"));
+        assert_eq!(resolve_css("css/app.css", "data:image/png;base64,AAAA"), None);
+        assert_eq!(resolve_css("css/app.css", "https://example.com/a.css"), None);
+        assert_eq!(resolve_css("css/app.css", "/a.css"), None);
+        assert_eq!(resolve_css("css/app.css", "#filter"), None);
+    }
+
+    #[test]
+    fn fnv1a_hash() {
+        assert_eq!(fnv1a(""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a("a"), 0xaf63dc4c8601ec8c);
+        assert_ne!(fnv1a("js/a.js"), fnv1a("js/b.js"));
+    }
+
+    #[test]
+    fn file_idents() {
+        let file = |path: &str| File { path: path.to_string(), full_path: PathBuf::new(), source: String::new() };
+        assert_eq!(file("js/lib/c.js").ident(), "js-lib-c-js");
+        assert_eq!(file("a/b.js").ident(), file("a-b.js").ident());
+    }
+
+    #[test]
+    fn parse_module_statements() {
+        let source = "import { b } from \"./b.js\";\nimport \"./lib/c.js\";\nimport x from \"lit\";\nexport * from './b.js';\nconst a = 1;\n";
+        let (source, references) = parse_module("js/a.js", source, &ids()).unwrap();
+        assert_eq!(source, ";\n;\nimport x from \"lit\";\n;\nconst a = 1;\n");
+        assert_eq!(
+            references,
+            vec![
+                (Reference::Module(Some("{ b }".to_string())), fnv1a("js/b.js")),
+                (Reference::Module(None), fnv1a("js/lib/c.js")),
+                (Reference::Export("*".to_string()), fnv1a("js/b.js")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_module_statements_on_one_line() {
+        let source = "import { b } from \"./b.js\"; import \"./lib/c.js\"; export { b };";
+        let (source, references) = parse_module("js/a.js", source, &ids()).unwrap();
+        assert_eq!(source, ";; export { b };");
+        assert_eq!(
+            references,
+            vec![(Reference::Module(Some("{ b }".to_string())), fnv1a("js/b.js")), (Reference::Module(None), fnv1a("js/lib/c.js"))]
+        );
+    }
+
+    #[test]
+    fn parse_module_ignores_comments_and_dynamic_imports() {
+        let source = "// import \"./b.js\";\nconst c = import(\"./lib/c.js\");\n";
+        let (text, references) = parse_module("js/a.js", source, &ids()).unwrap();
+        assert_eq!(text, source);
+        assert!(references.is_empty());
+    }
+
+    #[test]
+    fn parse_module_stylesheets_and_assets() {
+        let source = "import \"../css/base.css\";\nimport \"./b.js\";\n";
+        let (source, references) = parse_module("js/a.js", source, &ids()).unwrap();
+        assert_eq!(source, ";\n;\n");
+        assert_eq!(references, vec![(Reference::Style, fnv1a("css/base.css")), (Reference::Module(None), fnv1a("js/b.js"))]);
+
+        assert!(parse_module("js/a.js", "import base from \"../css/base.css\";", &ids()).is_err());
+        assert!(parse_module("js/a.js", "import font from \"../fonts/inter.woff2\";", &ids()).is_err());
+    }
+
+    #[test]
+    fn parse_css_references() {
+        let source = "@import \"base.css\" screen;\n@import url(missing.css);\n@font-face { src: url('../fonts/inter.woff2?v=2') format('woff2'); }\n.a { background: url(data:image/png;base64,AAAA); }";
+        let references = parse_css("css/app.css", source, &ids());
+        assert_eq!(
+            references,
+            vec![
+                (Reference::Import("base.css".to_string()), fnv1a("css/base.css")),
+                (Reference::Url("../fonts/inter.woff2?v=2".to_string()), fnv1a("fonts/inter.woff2")),
+            ]
+        );
+    }
+
+    #[test]
+    fn collect_referenced_assets() {
+        let dir = std::env::temp_dir().join(format!("workflow-dom-macros-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("fonts")).unwrap();
+        std::fs::write(dir.join("fonts/inter.woff2"), [0u8]).unwrap();
+        std::fs::write(dir.join("fonts/unused.png"), [0u8]).unwrap();
+        let source = "@font-face { src: url(../fonts/inter.woff2); } .a { background: url(../fonts/missing.png); }";
+        let mut files = vec![File { path: "css/app.css".to_string(), full_path: PathBuf::new(), source: source.to_string() }];
+        collect_css_assets(&dir, &mut files);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files.iter().map(|file| file.path.as_str()).collect::<Vec<_>>(), vec!["css/app.css", "fonts/inter.woff2"]);
+    }
+
+    #[test]
+    fn detect_modules() {
+        assert!(module().is_match("const a = 1;\nexport const b = 2;"));
+        assert!(!module().is_match("window.importer = 1;"));
+    }
+}
@@ -0,0 +1,32 @@
+//!
+//! Macros for the [`workflow_dom`](https://docs.rs/workflow-dom) crate.
+//!
+
+use proc_macro::TokenStream;
+mod content;
+
+/// Declare [`loader`](https://docs.rs/workflow-dom/latest/workflow_dom/loader/index.html)
+/// content from JavaScript and CSS files.
+///
+/// Accepts a comma-separated list of file or directory paths relative
+/// to the crate's `Cargo.toml`. Directories are scanned recursively for
+/// `.js`, `.mjs` and `.css` files. Binary assets (fonts and images such as
+/// `.woff2`, `.png` or `.svg`) are embedded only if listed explicitly or
+/// referenced by a `url()` function of an included stylesheet, so unrelated
+/// files in scanned directories do not end up in the binary. Each file is
+/// embedded into the binary, relative `import` and `export ... from` statements
+/// and CSS `@import` rules and `url()` functions referring to other files in
+/// the list are converted into loader references and each file receives a
+/// stable `Id` derived from its path. A side-effect `import` of a listed
+/// stylesheet becomes a style reference loaded before the module; importing
+/// bindings from a stylesheet or importing an asset is a compile error.
+///
+/// The macro yields an array of `(Id, Arc<Content>)` tuples:
+///
+/// ```ignore
+/// let ctx = workflow_dom::loader::declare(&include_content!("js/lit", "css/app.css"));
+/// ```
+#[proc_macro]
+pub fn include_content(input: TokenStream) -> TokenStream {
+    content::include_content(input)
+}
@@ -0,0 +1,45 @@
+//!
+//! Helpers for binary assets (fonts, images) embedded in the WASM binary.
+//!
+//! Assets are exposed to the page as blob URLs (see [`create_blob_url`](crate::blob::create_blob_url)
+//! or [`Context::asset_url`](crate::loader::Context::asset_url)). Fonts can be registered
+//! with a document using the [`FontFace`](https://developer.mozilla.org/en-US/docs/Web/API/FontFace) API.
+//!
+//! Example:
+//!
+//! ```rust
+//! let url = create_blob_url(ICON_SVG, Some("image/svg+xml"))?;
+//! image.set_src(&url);
+//! load_font(&document(), "Inter", &create_blob_url(INTER_WOFF2, Some("font/woff2"))?, None).await?;
+//! ```
+//!
+
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::JsCast;
+use web_sys::Document;
+use crate::result::Result;
+use crate::utils::{window, call_async};
+
+/// Returns `true` if the environment supports the `FontFace` API
+pub fn is_font_face_supported() -> bool {
+    Reflect::get(&window(), &"FontFace".into())
+        .map(|class| !class.is_undefined())
+        .unwrap_or(false)
+}
+
+/// Load the font `family` from `url` and add it to the fonts of the `document`.
+/// Optional `descriptors` (such as `weight` or `style`) are passed to the
+/// `FontFace` constructor. Returns the loaded `FontFace` object.
+pub async fn load_font(document : &Document, family : &str, url : &str, descriptors : Option<&Object>) -> Result<Object> {
+    let class = Reflect::get(&window(), &"FontFace".into())?;
+    let source = format!("url(\"{}\")", url);
+    let descriptors = descriptors.cloned().unwrap_or_default();
+    let args = Array::of3(&family.into(), &source.into(), &descriptors);
+    let face = Reflect::construct(class.unchecked_ref(), &args)?;
+    call_async(&face, "load", &[]).await?;
+
+    let fonts = Reflect::get(document, &"fonts".into())?;
+    let add = Reflect::get(&fonts, &"add".into())?;
+    Reflect::apply(add.unchecked_ref(), &fonts, &Array::of1(&face))?;
+    Ok(face.unchecked_into())
+}
@@ -0,0 +1,56 @@
+//!
+//! Registry of [`Blob`](https://developer.mozilla.org/en-US/docs/Web/API/Blob)
+//! object URLs created by this crate.
+//!
+//! Object URLs keep their blobs alive until revoked. All URLs created
+//! by [`inject`](crate::inject) and [`loader`](crate::loader) are tracked
+//! here so that they can be revoked once no longer needed.
+//!
+
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use js_sys::{Array,Uint8Array};
+use web_sys::{Url,Blob};
+use crate::result::Result;
+
+static URLS : Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Create a blob object URL from the `data` buffer with an
+/// optional MIME `content_type`. The URL is tracked until revoked
+/// via [`revoke_blob_url`].
+pub fn create_blob_url(data : &[u8], content_type : Option<&str>) -> Result<String> {
+    let args = Array::new_with_length(1);
+    args.set(0, unsafe { Uint8Array::view(data).into() });
+    let blob = match content_type {
+        Some(content_type) => {
+            let mut options = web_sys::BlobPropertyBag::new();
+            options.type_(content_type);
+            Blob::new_with_u8_array_sequence_and_options(&args, &options)?
+        },
+        None => Blob::new_with_u8_array_sequence(&args)?
+    };
+    let url = Url::create_object_url_with_blob(&blob)?;
+    URLS.lock().unwrap().insert(url.clone());
+    Ok(url)
+}
+
+/// Revoke a blob object URL. Returns `false` if the URL
+/// was not created by this crate or has already been revoked.
+pub fn revoke_blob_url(url : &str) -> Result<bool> {
+    if URLS.lock().unwrap().remove(url) {
+        Url::revoke_object_url(url)?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// Returns `true` if the URL has been created by this crate and not yet revoked
+pub fn is_live(url : &str) -> bool {
+    URLS.lock().unwrap().contains(url)
+}
+
+/// Returns the number of live (not revoked) blob object URLs
+pub fn live_blob_urls() -> usize {
+    URLS.lock().unwrap().len()
+}
@@ -0,0 +1,119 @@
+//!
+//! Cancellation and timeout support for pending injections.
+//!
+//! A [`Cancel`] handle can be passed to [`inject_blob_with_cancel`](crate::inject::inject_blob_with_cancel)
+//! or obtained from [`Context::cancel`](crate::loader::Context::cancel). Cancelling
+//! (or timing out) removes the half-injected element from DOM. Blob URLs are
+//! left to their owner, as they may be shared (e.g. URLs listed in an import map).
+//!
+
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use futures::{select, FutureExt};
+use web_sys::Element;
+use workflow_core::channel::{Channel, Receiver};
+use workflow_core::task::sleep;
+use workflow_core::time::Instant;
+use crate::error::Error;
+use crate::result::Result;
+
+struct Inner {
+    element : Mutex<Option<Element>>,
+    cancelled : AtomicBool,
+    channel : Channel<()>,
+}
+
+unsafe impl Send for Inner {}
+unsafe impl Sync for Inner {}
+
+/// Cancellation handle for a pending injection
+#[derive(Clone)]
+pub struct Cancel {
+    inner : Arc<Inner>,
+}
+
+unsafe impl Send for Cancel {}
+unsafe impl Sync for Cancel {}
+
+impl Default for Cancel {
+    fn default() -> Self {
+        Cancel {
+            inner : Arc::new(Inner {
+                element : Mutex::new(None),
+                cancelled : AtomicBool::new(false),
+                channel : Channel::oneshot(),
+            })
+        }
+    }
+}
+
+impl Cancel {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Cancel the pending injection, removing the injected element
+    /// from DOM. The awaiting future resolves with [`Error::Cancelled`].
+    pub fn cancel(&self) -> Result<()> {
+        if !self.inner.cancelled.swap(true, Ordering::SeqCst) {
+            self.inner.channel.sender.try_send(()).ok();
+        }
+        self.remove_element();
+        Ok(())
+    }
+
+    /// Associate the injected element with this handle
+    pub(crate) fn bind(&self, element : &Element) -> Result<()> {
+        self.inner.element.lock().unwrap().replace(element.clone());
+        if self.is_cancelled() {
+            self.remove_element();
+        }
+        Ok(())
+    }
+
+    /// Remove the bound element from DOM without revoking its URL
+    pub(crate) fn remove_element(&self) {
+        if let Some(element) = self.inner.element.lock().unwrap().take() {
+            element.remove();
+        }
+    }
+
+    /// Run the `future` to completion, failing with [`Error::Timeout`]
+    /// if `timeout` elapses or [`Error::Cancelled`] if cancelled.
+    pub(crate) async fn until<T>(&self, ident : &str, future : impl Future<Output = Result<T>>, timeout : Option<Duration>) -> Result<T> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled(ident.to_string()));
+        }
+
+        let start = Instant::now();
+        let delay = async move {
+            match timeout {
+                Some(timeout) => sleep(timeout).await,
+                None => futures::future::pending::<()>().await,
+            }
+        };
+
+        select! {
+            result = future.fuse() => result,
+            _ = self.inner.channel.receiver.recv().fuse() => Err(Error::Cancelled(ident.to_string())),
+            _ = delay.fuse() => {
+                self.remove_element();
+                Err(Error::Timeout { ident : ident.to_string(), elapsed : start.elapsed() })
+            }
+        }
+    }
+
+    /// Wait for the `receiver` to resolve, failing with [`Error::Timeout`]
+    /// if `timeout` elapses or [`Error::Cancelled`] if cancelled.
+    pub(crate) async fn wait<T>(&self, ident : &str, receiver : &Receiver<T>, timeout : Option<Duration>) -> Result<T> {
+        self.until(ident, async { Ok(receiver.recv().await?) }, timeout).await
+    }
+}
@@ -0,0 +1,102 @@
+//!
+//! Decompression of compressed embedded content.
+//!
+//! Content embedded in the WASM binary can be declared compressed (e.g. via
+//! [`include_bytes`] of a `.gz` file) and is decompressed at injection time.
+//! Decompression uses [`DecompressionStream`](https://developer.mozilla.org/en-US/docs/Web/API/DecompressionStream)
+//! where the browser supports the format. The `compression-fallback` cargo feature
+//! enables a Rust implementation used otherwise (e.g. for Brotli, which browsers
+//! do not support); it is opt-in as it noticeably increases the binary size.
+//!
+//! Example:
+//!
+//! ```rust
+//! inject_blob(Content::Compressed(Compression::Gzip, Box::new(Content::Script(None, EDITOR_JS_GZ)))).await?;
+//! ```
+//!
+
+#[cfg(feature = "compression-fallback")]
+use std::io::Read;
+use js_sys::{Array, Reflect, Uint8Array};
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use crate::result::Result;
+use crate::utils::{window, call, call_async, local};
+
+/// Compression format of the embedded content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Gzip (RFC 1952)
+    Gzip,
+    /// Zlib wrapped deflate (RFC 1950)
+    Deflate,
+    /// Raw deflate (RFC 1951)
+    DeflateRaw,
+    /// Brotli (RFC 7932)
+    Brotli,
+}
+
+impl Compression {
+    /// Format name accepted by `DecompressionStream`
+    pub fn format(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Deflate => "deflate",
+            Compression::DeflateRaw => "deflate-raw",
+            Compression::Brotli => "brotli",
+        }
+    }
+
+    /// Returns `true` if `DecompressionStream` supports this format
+    pub fn is_supported(&self) -> bool {
+        self.stream().is_ok()
+    }
+
+    /// Decompress `data`, using `DecompressionStream` if it supports
+    /// this format, or the Rust implementation otherwise (if enabled
+    /// by the `compression-fallback` feature)
+    pub async fn decompress(&self, data : &[u8]) -> Result<Vec<u8>> {
+        if !self.is_supported() {
+            #[cfg(feature = "compression-fallback")]
+            return self.decompress_sync(data);
+            #[cfg(not(feature = "compression-fallback"))]
+            return Err(format!("unable to decompress {} content: the format is not supported by DecompressionStream (enable the `compression-fallback` feature)", self.format()).into());
+        }
+        let compression = *self;
+        let data = data.to_vec();
+        local(async move { compression.decompress_stream(&data).await }).await
+    }
+
+    /// Decompress `data` using the Rust implementation
+    #[cfg(feature = "compression-fallback")]
+    pub fn decompress_sync(&self, data : &[u8]) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        let result = match self {
+            Compression::Gzip => flate2::read::GzDecoder::new(data).read_to_end(&mut buffer),
+            Compression::Deflate => flate2::read::ZlibDecoder::new(data).read_to_end(&mut buffer),
+            Compression::DeflateRaw => flate2::read::DeflateDecoder::new(data).read_to_end(&mut buffer),
+            Compression::Brotli => brotli_decompressor::Decompressor::new(data, 4096).read_to_end(&mut buffer),
+        };
+        result.map_err(|err| format!("unable to decompress {} content: {}", self.format(), err))?;
+        Ok(buffer)
+    }
+
+    fn stream(&self) -> Result<JsValue> {
+        let class = Reflect::get(&window(), &"DecompressionStream".into())?;
+        if class.is_undefined() {
+            return Err("DecompressionStream is not supported".into());
+        }
+        // the constructor throws a TypeError for unsupported formats
+        Ok(Reflect::construct(class.unchecked_ref(), &Array::of1(&self.format().into()))?)
+    }
+
+    async fn decompress_stream(&self, data : &[u8]) -> Result<Vec<u8>> {
+        let blob = web_sys::Blob::new_with_u8_array_sequence(&Array::of1(&Uint8Array::from(data)))?;
+        let stream = call(&blob, "stream", &[])?;
+        let stream = call(&stream, "pipeThrough", &[self.stream()?])?;
+        let class = Reflect::get(&window(), &"Response".into())?;
+        let response = Reflect::construct(class.unchecked_ref(), &Array::of1(&stream))?;
+        let buffer = call_async(&response, "arrayBuffer", &[]).await?;
+        Ok(Uint8Array::new(&buffer).to_vec())
+    }
+}
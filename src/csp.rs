@@ -0,0 +1,119 @@
+//!
+//! [Content Security Policy](https://developer.mozilla.org/en-US/docs/Web/HTTP/CSP)
+//! support for injected elements.
+//!
+//! The [`Policy`] configured via [`set_policy`] applies to every `<script>`,
+//! `<link>` and `<style>` element created by this crate. It allows setting a
+//! `nonce` attribute, computing [subresource integrity](https://developer.mozilla.org/en-US/docs/Web/Security/Subresource_Integrity)
+//! hashes from the injected buffers and injecting content as inline text
+//! in environments where `blob:` URLs are not allowed by the policy.
+//!
+//! Example:
+//!
+//! ```rust
+//! csp::set_policy(Policy::default().with_nonce("r4nd0m").with_integrity(Integrity::Sha384));
+//! ```
+//!
+
+use std::sync::Mutex;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use sha2::{Digest, Sha256, Sha384};
+use web_sys::{Element, Event};
+use crate::result::Result;
+
+/// Subresource integrity hash algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Integrity {
+    Sha256,
+    Sha384,
+}
+
+impl Integrity {
+    /// Compute integrity metadata (`sha384-<base64>`) of `data`
+    pub fn digest(&self, data : &[u8]) -> String {
+        match self {
+            Integrity::Sha256 => format!("sha256-{}", STANDARD.encode(Sha256::digest(data))),
+            Integrity::Sha384 => format!("sha384-{}", STANDARD.encode(Sha384::digest(data))),
+        }
+    }
+}
+
+/// Content Security Policy settings applied to injected elements
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    /// `nonce` attribute set on each created element
+    pub nonce : Option<String>,
+    /// Algorithm used to compute the `integrity` attribute of
+    /// `<script>` and `<link>` elements
+    pub integrity : Option<Integrity>,
+    /// Inject content as inline text instead of `blob:` URLs.
+    /// Inline content does not support loader module references
+    /// (imports are resolved via `blob:` URLs) and inline modules
+    /// report `load` upon insertion, before their (deferred) evaluation.
+    pub inline : bool,
+}
+
+impl Policy {
+    pub fn with_nonce(mut self, nonce : &str) -> Self {
+        self.nonce = Some(nonce.to_string());
+        self
+    }
+
+    pub fn with_integrity(mut self, integrity : Integrity) -> Self {
+        self.integrity = Some(integrity);
+        self
+    }
+
+    pub fn with_inline(mut self, inline : bool) -> Self {
+        self.inline = inline;
+        self
+    }
+}
+
+static POLICY : Mutex<Option<Policy>> = Mutex::new(None);
+
+/// Set the policy applied to all subsequently injected elements
+pub fn set_policy(policy : Policy) {
+    POLICY.lock().unwrap().replace(policy);
+}
+
+/// Returns the current policy
+pub fn policy() -> Policy {
+    POLICY.lock().unwrap().clone().unwrap_or_default()
+}
+
+/// Returns `true` if content should be injected as inline text
+pub fn is_inline() -> bool {
+    POLICY.lock().unwrap().as_ref().map(|policy| policy.inline).unwrap_or(false)
+}
+
+/// Apply the `nonce` and `integrity` attributes to the `element`
+/// referring to a resource containing `data`
+pub(crate) fn apply(element : &Element, data : &[u8]) -> Result<()> {
+    let policy = policy();
+    if let Some(nonce) = &policy.nonce {
+        element.set_attribute("nonce", nonce)?;
+    }
+    if let Some(integrity) = &policy.integrity {
+        element.set_attribute("integrity", &integrity.digest(data))?;
+    }
+    Ok(())
+}
+
+/// Apply the `nonce` attribute to an inline `element`
+pub(crate) fn apply_nonce(element : &Element) -> Result<()> {
+    if let Some(nonce) = &policy().nonce {
+        element.set_attribute("nonce", nonce)?;
+    }
+    Ok(())
+}
+
+/// Inline scripts do not dispatch the `load` event, so it is dispatched
+/// once the script has been appended (classic scripts are executed
+/// synchronously upon insertion). Inline `<style>` elements dispatch
+/// `load` natively and must not use this function.
+pub(crate) fn dispatch_load(element : &Element) -> Result<()> {
+    element.dispatch_event(&Event::new("load")?)?;
+    Ok(())
+}
@@ -1,6 +1,7 @@
 //!
 //! Errors return by the [`workflow_dom`](super) module
 //! 
+use std::time::Duration;
 use thiserror::Error;
 use wasm_bindgen::JsValue;
 use workflow_core::channel::RecvError;
@@ -15,7 +16,47 @@ pub enum Error {
     #[error("{0:?}")]
     JsValue(JsValue),
     #[error("{0}")]
-    RecvError(RecvError) //#[from] workflow_core::channel::RecvError),
+    RecvError(RecvError), //#[from] workflow_core::channel::RecvError),
+    /// Error produced when an injected element dispatches an `error` event;
+    /// carries the element `ident` and the received event
+    #[error("unable to load `{0}`: {1:?}")]
+    Load(String, JsValue),
+    /// Loading of `ident` did not complete within the allotted time
+    #[error("timeout loading `{ident}` after {} msec", elapsed.as_millis())]
+    Timeout { ident : String, elapsed : Duration },
+    /// Loading of `ident` has been cancelled
+    #[error("loading of `{0}` has been cancelled")]
+    Cancelled(String),
+    /// Content references itself
+    #[error("content `{0}` references itself")]
+    SelfReference(String),
+    /// Content references an id that has not been declared
+    #[error("content `{0}` references undeclared id {1}")]
+    DanglingReference(String, u64),
+    /// Circular dependency between declared content; carries
+    /// the idents forming the cycle
+    #[error("circular dependency: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+    /// Reference kind does not match the type of the referencing
+    /// or the referenced content
+    #[error("content `{0}` can not reference `{1}`: {2}")]
+    ReferenceType(String, String, String),
+    /// Content id has not been declared
+    #[error("content id {0} is not declared")]
+    NotFound(u64),
+    /// Injection of `ident` has been aborted due to a failure
+    /// to load its `dependency`
+    #[error("unable to load `{ident}`: dependency `{dependency}` failed: {source}")]
+    DependencyFailed { ident : String, dependency : String, source : Box<Error> },
+    /// Fetch request for `url` failed with HTTP `status`
+    #[error("unable to fetch `{url}`: HTTP status {status}")]
+    Fetch { url : String, status : u16 },
+    /// Fetched content does not match its integrity metadata
+    #[error("integrity mismatch for `{url}`: expected {expected}, got {actual}")]
+    Integrity { url : String, expected : String, actual : String },
+    /// Imported module does not provide the requested export
+    #[error("module `{0}` does not export `{1}`")]
+    MissingExport(String, String),
 }
 
 unsafe impl Send for Error {}
@@ -0,0 +1,196 @@
+//!
+//! Content fetched over the network and cached in the
+//! [Cache Storage](https://developer.mozilla.org/en-US/docs/Web/API/CacheStorage).
+//!
+//! A [`Fetch`] source describes the `url` of the content and its `version`.
+//! Fetched content is stored in the cache along with its version; subsequent
+//! requests are served from the cache until the version changes, at which point
+//! the content is fetched again and the cached entry is replaced. Content can
+//! optionally be verified against [subresource integrity](https://developer.mozilla.org/en-US/docs/Web/Security/Subresource_Integrity)
+//! metadata. Without Cache Storage support (e.g. in insecure contexts or if the
+//! cache can not be opened in private browsing) content is fetched on each request.
+//!
+//! Example:
+//!
+//! ```rust
+//! let source = Fetch::new("/bundles/editor.js", "1.4.2").with_integrity("sha384-...");
+//! let text = source.text().await?;
+//! ```
+//!
+
+use js_sys::{Array, Function, Object, Reflect, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+use crate::csp::Integrity;
+use crate::error::Error;
+use crate::result::Result;
+use crate::utils::{window, call_async, local};
+
+/// Default name of the cache used to store fetched content
+pub const CACHE_NAME : &str = "workflow-dom";
+
+/// Header carrying the version of the cached content
+const VERSION_HEADER : &str = "x-workflow-dom-version";
+
+/// Content source fetched by URL
+#[derive(Debug, Clone)]
+pub struct Fetch {
+    pub url : String,
+    pub version : String,
+    /// Subresource integrity metadata (`sha384-<base64>`)
+    /// the fetched content is verified against
+    pub integrity : Option<String>,
+    /// Name of the cache storing the content;
+    /// `None` disables caching
+    pub cache : Option<String>,
+}
+
+impl Fetch {
+
+    /// Create a source fetching `url`, cached under `version`
+    pub fn new(url : &str, version : &str) -> Self {
+        Fetch {
+            url : url.to_string(),
+            version : version.to_string(),
+            integrity : None,
+            cache : Some(CACHE_NAME.to_string()),
+        }
+    }
+
+    pub fn with_integrity(mut self, integrity : &str) -> Self {
+        self.integrity = Some(integrity.to_string());
+        self
+    }
+
+    pub fn with_cache(mut self, name : &str) -> Self {
+        self.cache = Some(name.to_string());
+        self
+    }
+
+    pub fn without_cache(mut self) -> Self {
+        self.cache = None;
+        self
+    }
+
+    /// Returns the content, fetching it if it is not cached
+    /// or if the cached version differs from `version`
+    pub async fn text(&self) -> Result<String> {
+        let bytes = self.bytes().await?;
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+
+    /// Returns the content as bytes (see [`Fetch::text`])
+    pub async fn bytes(&self) -> Result<Vec<u8>> {
+        // JS futures are not `Send`, so they are driven by a local
+        // task, allowing this future to be used by the loader
+        let source = self.clone();
+        local(async move { source.bytes_impl().await }).await
+    }
+
+    async fn bytes_impl(&self) -> Result<Vec<u8>> {
+        // cache failures fall back to an uncached fetch
+        let cache = match &self.cache {
+            Some(name) => open(name).await.unwrap_or(None),
+            None => None,
+        };
+
+        if let Some(cache) = &cache {
+            if let Ok(Some(bytes)) = self.cached(cache).await {
+                if self.verify(&bytes).is_ok() {
+                    return Ok(bytes);
+                }
+            }
+        }
+
+        let response = call_async(&window(), "fetch", &[self.url.as_str().into()]).await?;
+        let status = Reflect::get(&response, &"status".into())?.as_f64().unwrap_or_default() as u16;
+        if !Reflect::get(&response, &"ok".into())?.is_truthy() {
+            return Err(Error::Fetch { url : self.url.clone(), status });
+        }
+        let bytes = Uint8Array::new(&call_async(&response, "arrayBuffer", &[]).await?).to_vec();
+        self.verify(&bytes)?;
+
+        if let Some(cache) = &cache {
+            self.store(cache, &bytes).await.ok();
+        }
+
+        Ok(bytes)
+    }
+
+    /// Returns cached content if its version matches
+    async fn cached(&self, cache : &JsValue) -> Result<Option<Vec<u8>>> {
+        let response = call_async(cache, "match", &[self.url.as_str().into()]).await?;
+        if response.is_undefined() {
+            return Ok(None);
+        }
+        let headers = Reflect::get(&response, &"headers".into())?;
+        let get = Reflect::get(&headers, &"get".into())?;
+        let version = get.unchecked_ref::<Function>().call1(&headers, &VERSION_HEADER.into())?;
+        if version.as_string().as_deref() != Some(self.version.as_str()) {
+            return Ok(None);
+        }
+        let buffer = call_async(&response, "arrayBuffer", &[]).await?;
+        Ok(Some(Uint8Array::new(&buffer).to_vec()))
+    }
+
+    /// Store `bytes` in the cache, replacing any previous version
+    async fn store(&self, cache : &JsValue, bytes : &[u8]) -> Result<()> {
+        let headers = Object::new();
+        Reflect::set(&headers, &VERSION_HEADER.into(), &self.version.as_str().into())?;
+        let init = Object::new();
+        Reflect::set(&init, &"headers".into(), &headers)?;
+        let class = Reflect::get(&window(), &"Response".into())?;
+        let body = Uint8Array::from(bytes);
+        let response = Reflect::construct(class.unchecked_ref(), &Array::of2(&body, &init))?;
+        call_async(cache, "put", &[self.url.as_str().into(), response]).await?;
+        Ok(())
+    }
+
+    /// Verify `bytes` against the integrity metadata
+    fn verify(&self, bytes : &[u8]) -> Result<()> {
+        let expected = match &self.integrity {
+            Some(integrity) => integrity,
+            None => return Ok(()),
+        };
+        let integrity = if expected.starts_with("sha256-") {
+            Integrity::Sha256
+        } else if expected.starts_with("sha384-") {
+            Integrity::Sha384
+        } else {
+            return Err(format!("unsupported integrity metadata `{}`", expected).into());
+        };
+        let actual = integrity.digest(bytes);
+        if &actual != expected {
+            return Err(Error::Integrity { url : self.url.clone(), expected : expected.clone(), actual });
+        }
+        Ok(())
+    }
+}
+
+/// Returns `true` if the environment supports Cache Storage
+pub fn is_supported() -> bool {
+    caches().is_some()
+}
+
+/// Delete the cache `name` along with all content stored in it
+pub async fn clear_cache(name : &str) -> Result<bool> {
+    let name = name.to_string();
+    local(async move {
+        match caches() {
+            Some(caches) => Ok(call_async(&caches, "delete", &[name.into()]).await?.is_truthy()),
+            None => Ok(false),
+        }
+    }).await
+}
+
+fn caches() -> Option<JsValue> {
+    Reflect::get(&window(), &"caches".into())
+        .ok()
+        .filter(|caches| !caches.is_undefined() && !caches.is_null())
+}
+
+async fn open(name : &str) -> Result<Option<JsValue>> {
+    match caches() {
+        Some(caches) => Ok(Some(call_async(&caches, "open", &[name.into()]).await?)),
+        None => Ok(None),
+    }
+}
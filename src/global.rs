@@ -0,0 +1,73 @@
+//!
+//! Access to globals defined by injected classic scripts.
+//!
+//! Third-party scripts typically attach a global (such as `window.Chart`)
+//! and may complete their initialization after the `load` event. The functions
+//! in this module resolve a dotted global path (e.g. `google.maps`) on the window
+//! of a [`Target`] document (such as the window of an iframe), optionally polling
+//! until it is defined.
+//!
+//! Example:
+//!
+//! ```rust
+//! inject_blob(Content::Script(None, CHART_JS)).await?;
+//! let chart = wait_for_global(&Target::default(), "Chart", POLL_INTERVAL, Some(Duration::from_secs(5))).await?;
+//! ```
+//!
+
+use std::time::Duration;
+use js_sys::Reflect;
+use wasm_bindgen::JsValue;
+use workflow_core::task::sleep;
+use workflow_core::time::Instant;
+use crate::error::Error;
+use crate::result::Result;
+use crate::target::Target;
+
+/// Default interval between checks of a global
+pub const POLL_INTERVAL : Duration = Duration::from_millis(25);
+
+/// Limit on waiting for a global if no timeout is supplied
+pub const GLOBAL_TIMEOUT : Duration = Duration::from_secs(10);
+
+/// Resolve the dotted global `path` on the window of the `target`. Returns
+/// `None` if any segment of the path is `undefined` or `null`.
+pub fn resolve_global(target : &Target, path : &str) -> Option<JsValue> {
+    let mut value : JsValue = target.window().ok()?.into();
+    for name in path.split('.') {
+        value = Reflect::get(&value, &name.into()).ok()?;
+        if value.is_undefined() || value.is_null() {
+            return None;
+        }
+    }
+    Some(value)
+}
+
+/// Returns `true` if the dotted global `path` is defined
+/// on the window of the `target`
+pub fn has_global(target : &Target, path : &str) -> bool {
+    resolve_global(target, path).is_some()
+}
+
+/// Wait until the dotted global `path` is defined on the window of the `target`,
+/// checking it every `interval`. Fails with [`Error::Timeout`] if the global is not
+/// defined within `timeout` (or [`GLOBAL_TIMEOUT`] if `None`).
+pub async fn wait_for_global(target : &Target, path : &str, interval : Duration, timeout : Option<Duration>) -> Result<JsValue> {
+    wait_until_defined(target, path, interval, timeout).await?;
+    resolve_global(target, path).ok_or_else(|| Error::String(format!("global `{}` is not defined", path)))
+}
+
+/// Wait until the global `path` is defined without holding
+/// JS values across await points, so that the future is `Send`
+pub(crate) async fn wait_until_defined(target : &Target, path : &str, interval : Duration, timeout : Option<Duration>) -> Result<()> {
+    let timeout = timeout.unwrap_or(GLOBAL_TIMEOUT);
+    let start = Instant::now();
+    while !has_global(target, path) {
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Err(Error::Timeout { ident : path.to_string(), elapsed });
+        }
+        sleep(interval).await;
+    }
+    Ok(())
+}
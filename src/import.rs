@@ -0,0 +1,94 @@
+//!
+//! Dynamic [`import()`](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Operators/import)
+//! of JavaScript modules, providing Rust access to module exports without
+//! exposing them via globals.
+//!
+//! Example:
+//!
+//! ```rust
+//! let module = context().import(&CHART_MODULE_ID).await?;
+//! let chart = module.construct("Chart", &Array::of2(&canvas, &config))?;
+//! ```
+//!
+
+use js_sys::{Array, Function, Object, Promise, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use crate::error::Error;
+use crate::result::Result;
+
+#[wasm_bindgen(inline_js = "export function dynamic_import(url) { return import(url); }")]
+extern "C" {
+    #[wasm_bindgen(catch)]
+    fn dynamic_import(url : &str) -> std::result::Result<Promise, JsValue>;
+}
+
+/// Import the module at `url` (such as a module blob URL)
+/// and return its namespace
+pub async fn import(url : &str) -> Result<ModuleNamespace> {
+    let namespace = JsFuture::from(dynamic_import(url)?).await?;
+    Ok(ModuleNamespace { ident : url.to_string(), namespace : namespace.unchecked_into() })
+}
+
+/// Namespace object of an imported module
+#[derive(Clone, Debug)]
+pub struct ModuleNamespace {
+    ident : String,
+    namespace : Object,
+}
+
+unsafe impl Send for ModuleNamespace {}
+unsafe impl Sync for ModuleNamespace {}
+
+impl ModuleNamespace {
+
+    pub(crate) fn with_ident(mut self, ident : &str) -> Self {
+        self.ident = ident.to_string();
+        self
+    }
+
+    /// Underlying module namespace object
+    pub fn namespace(&self) -> &Object {
+        &self.namespace
+    }
+
+    /// Returns `true` if the module exports `name`
+    pub fn has(&self, name : &str) -> bool {
+        Reflect::has(&self.namespace, &name.into()).unwrap_or(false)
+    }
+
+    /// Returns the export `name`
+    pub fn get(&self, name : &str) -> Result<JsValue> {
+        if !self.has(name) {
+            return Err(Error::MissingExport(self.ident.clone(), name.to_string()));
+        }
+        Ok(Reflect::get(&self.namespace, &name.into())?)
+    }
+
+    /// Returns the default export
+    pub fn default_export(&self) -> Result<JsValue> {
+        self.get("default")
+    }
+
+    /// Returns the exported function `name`
+    pub fn function(&self, name : &str) -> Result<Function> {
+        let value = self.get(name)?;
+        value.dyn_into::<Function>().map_err(|_| Error::String(format!("export `{}` of `{}` is not a function", name, self.ident)))
+    }
+
+    /// Returns the exported class `name` (its constructor function)
+    pub fn class(&self, name : &str) -> Result<Function> {
+        self.function(name)
+    }
+
+    /// Call the exported function `name` with `args`
+    pub fn call(&self, name : &str, args : &Array) -> Result<JsValue> {
+        Ok(self.function(name)?.apply(&JsValue::UNDEFINED, args)?)
+    }
+
+    /// Create an instance of the exported class `name` with `args`
+    pub fn construct(&self, name : &str, args : &Array) -> Result<Object> {
+        Ok(Reflect::construct(&self.class(name)?, args)?.unchecked_into())
+    }
+}
@@ -0,0 +1,96 @@
+//!
+//! [Import maps](https://developer.mozilla.org/en-US/docs/Web/HTML/Element/script/type/importmap)
+//! mapping module specifiers to URLs.
+//!
+//! An import map allows module sources to use bare specifiers
+//! (e.g. `import { html } from "lit"`) that resolve to injected blob URLs.
+//! Browsers only apply import maps injected before the first module
+//! is loaded, so the map must be injected prior to loading modules
+//! (see [`Context::import_map`](crate::loader::Context::import_map)).
+//!
+//! Example:
+//!
+//! ```rust
+//! ImportMap::new()
+//!     .with_import("lit", "https://cdn.jsdelivr.net/npm/lit@2/index.js")
+//!     .inject(&Target::default())?;
+//! ```
+//!
+
+use js_sys::{Function, Object, Reflect, JSON};
+use wasm_bindgen::JsCast;
+use web_sys::Element;
+use crate::csp;
+use crate::result::Result;
+use crate::target::Target;
+use crate::trusted_types;
+use crate::utils::window;
+
+/// Import map specifying URLs of module specifiers
+#[derive(Debug, Clone, Default)]
+pub struct ImportMap {
+    imports : Vec<(String, String)>,
+}
+
+impl ImportMap {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if the environment supports import maps
+    pub fn is_supported() -> bool {
+        Reflect::get(&window(), &"HTMLScriptElement".into())
+            .and_then(|class| {
+                let supports = Reflect::get(&class, &"supports".into())?;
+                supports.unchecked_ref::<Function>().call1(&class, &"importmap".into())
+            })
+            .map(|supported| supported.is_truthy())
+            .unwrap_or(false)
+    }
+
+    /// Map the module `specifier` to the `url`
+    pub fn with_import(mut self, specifier : &str, url : &str) -> Self {
+        self.insert(specifier, url);
+        self
+    }
+
+    /// Map the module `specifier` to the `url`, replacing a previous mapping
+    pub fn insert(&mut self, specifier : &str, url : &str) {
+        self.imports.retain(|(item,_)| item != specifier);
+        self.imports.push((specifier.to_string(), url.to_string()));
+    }
+
+    /// Returns the URL mapped to the `specifier`
+    pub fn get(&self, specifier : &str) -> Option<&str> {
+        self.imports.iter().find(|(item,_)| item == specifier).map(|(_,url)| url.as_str())
+    }
+
+    pub fn imports(&self) -> &[(String, String)] {
+        &self.imports
+    }
+
+    /// Serialize the import map as JSON
+    pub fn to_json(&self) -> Result<String> {
+        let imports = Object::new();
+        for (specifier, url) in self.imports.iter() {
+            Reflect::set(&imports, &specifier.into(), &url.into())?;
+        }
+        let map = Object::new();
+        Reflect::set(&map, &"imports".into(), &imports)?;
+        Ok(JSON::stringify(&map)?.into())
+    }
+
+    /// Inject the import map as a `<script type="importmap">` element into
+    /// the `target` document. Import maps apply to the entire document,
+    /// so element and shadow root targets inject into their document.
+    pub fn inject(&self, target : &Target) -> Result<Element> {
+        let document = target.document()?;
+        let script = document.create_element("script")?;
+        script.set_attribute("type", "importmap")?;
+        csp::apply_nonce(&script)?;
+        trusted_types::set_script_text(&script, &self.to_json()?)?;
+        Target::from(document).append(&script)?;
+        Ok(script)
+    }
+}
@@ -5,14 +5,26 @@
 //! 
 //! This can be used in conjunction with [`include_bytes`] macro to embed 
 //! JavaScript scripts, modules and CSS stylesheets directly within WASM 
-//! binary.
+//! binary. Content hosted externally can be injected by its URL using
+//! [`Content::ScriptUrl`], [`Content::ModuleUrl`] and [`Content::StyleUrl`].
 //! 
 
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
 use web_sys::Element;
-use web_sys::{Url,Blob};
-use js_sys::{Array,Uint8Array,Function};
+use js_sys::Function;
 use crate::result::*;
-use crate::utils::*;
+use crate::error::Error;
+use crate::cancel::Cancel;
+use crate::target::Target;
+use crate::options::Options;
+use crate::compression::Compression;
+use crate::blob::{create_blob_url, revoke_blob_url};
+use crate::csp;
+use crate::source_map;
+use crate::trusted_types;
+use crate::global::{wait_for_global, POLL_INTERVAL};
 use workflow_core::channel::oneshot;
 use workflow_wasm::callback::*;
 
@@ -25,18 +37,73 @@ pub enum Content<'content> {
     /// This data slice represents a JavaScript module
     Module(Option<&'content str>, &'content [u8]),
     /// This data slice represents a CSS stylesheet
-    Style(Option<&'content str>, &'content [u8])
+    Style(Option<&'content str>, &'content [u8]),
+    /// URL of a remote JavaScript script
+    ScriptUrl(Option<&'content str>, &'content str),
+    /// URL of a remote JavaScript module
+    ModuleUrl(Option<&'content str>, &'content str),
+    /// URL of a remote CSS stylesheet
+    StyleUrl(Option<&'content str>, &'content str),
+    /// Compressed script, module or stylesheet data decompressed prior
+    /// to injection (see [`compression`](crate::compression) for the
+    /// formats supported without the `compression-fallback` feature)
+    Compressed(Compression, Box<Content<'content>>),
 }
 
 /// Inject CSS stylesheed directly into DOM as a 
 /// [`<style>`](https://developer.mozilla.org/en-US/docs/Web/HTML/Element/style) 
-/// element using [`Element::set_inner_html`]
+/// element using `innerHTML` (see [`trusted_types`](crate::trusted_types))
 pub fn inject_css(css : &str) -> Result<()> {
-    let doc = document();
-    let head = doc.get_elements_by_tag_name("head").item(0).ok_or("Unable to locate head element")?;
-    let style_el = doc.create_element("style")?;
-    style_el.set_inner_html(css);
-    head.append_child(&style_el)?;
+    inject_css_into(&Target::default(), css)
+}
+
+/// Inject CSS stylesheet into the `target` (such as a [`web_sys::ShadowRoot`])
+/// as a `<style>` element
+pub fn inject_css_into(target : &Target, css : &str) -> Result<()> {
+    let style_el = target.document()?.create_element("style")?;
+    csp::apply_nonce(&style_el)?;
+    trusted_types::set_inner_html(&style_el, css)?;
+    target.append(&style_el)?;
+    Ok(())
+}
+
+impl<'content> Content<'content> {
+    fn ident(&self) -> &'content str {
+        match self {
+            Content::Script(id, _) | Content::Module(id, _) | Content::Style(id, _) => id.unwrap_or("blob"),
+            Content::ScriptUrl(id, url) | Content::ModuleUrl(id, url) | Content::StyleUrl(id, url) => id.unwrap_or(url),
+            Content::Compressed(_, content) => content.ident(),
+        }
+    }
+
+    /// Data buffer of the content
+    fn data(&self) -> Result<&'content [u8]> {
+        match self {
+            Content::Script(_, data) | Content::Module(_, data) | Content::Style(_, data) => Ok(data),
+            _ => Err(format!("content `{}` has no data buffer", self.ident()).into())
+        }
+    }
+
+    /// Content of the same type carrying `data`
+    fn with_data<'data>(&self, data : &'data [u8]) -> Result<Content<'data>>
+    where 'content : 'data
+    {
+        match self {
+            Content::Script(id, _) => Ok(Content::Script(*id, data)),
+            Content::Module(id, _) => Ok(Content::Module(*id, data)),
+            Content::Style(id, _) => Ok(Content::Style(*id, data)),
+            _ => Err(format!("content `{}` can not carry a data buffer", self.ident()).into())
+        }
+    }
+}
+
+/// Revoke the blob `url` once the `element` dispatches the `load` event.
+fn revoke_on_load(element : &Element, url : &str) -> Result<()> {
+    let url = url.to_string();
+    let closure = Closure::once_into_js(move || {
+        revoke_blob_url(&url).ok();
+    });
+    element.add_event_listener_with_callback("load", closure.unchecked_ref())?;
     Ok(())
 }
 
@@ -44,7 +111,8 @@ pub fn inject_css(css : &str) -> Result<()> {
 /// into DOM. The `content` argument carries the data buffer and 
 /// the content type represented by the [`Content`] struct.
 pub fn inject_blob_nowait(content: Content) ->  Result<()> {
-    inject_blob_with_callback::<CustomEventCallback>(content, None)
+    inject_blob_with_callback::<CustomEventCallback>(content, None)?;
+    Ok(())
 }
 
 /// Inject a [`Blob`](https://developer.mozilla.org/en-US/docs/Web/API/Blob)
@@ -52,35 +120,121 @@ pub fn inject_blob_nowait(content: Content) ->  Result<()> {
 /// the content type represented by the [`Content`] struct. This function
 /// returns a future that completes upon injection completion.
 pub async fn inject_blob(content:Content<'_>) -> Result<()> {
+    inject_blob_with_cancel(content, None, &Cancel::new()).await
+}
+
+/// Inject a [`Blob`](https://developer.mozilla.org/en-US/docs/Web/API/Blob)
+/// into DOM, failing with [`Error::Timeout`](crate::error::Error::Timeout)
+/// if the content does not load within `timeout`. The injected element is
+/// removed and its blob URL revoked upon timeout.
+pub async fn inject_blob_with_timeout(content:Content<'_>, timeout : Duration) -> Result<()> {
+    inject_blob_with_cancel(content, Some(timeout), &Cancel::new()).await
+}
+
+/// Inject a [`Blob`](https://developer.mozilla.org/en-US/docs/Web/API/Blob)
+/// into DOM with an optional `timeout`. The supplied [`Cancel`] handle
+/// can be used to abort the pending injection.
+pub async fn inject_blob_with_cancel(content:Content<'_>, timeout : Option<Duration>, cancel : &Cancel) -> Result<()> {
+    inject_blob_into(&Target::default(), content, &Options::default(), timeout, cancel).await
+}
+
+/// Inject a classic script [`Blob`](https://developer.mozilla.org/en-US/docs/Web/API/Blob)
+/// into DOM and wait for the dotted `global` path (e.g. `Chart`) defined by
+/// the script. Scripts initializing asynchronously are polled until the global
+/// is defined or `timeout` (by default [`GLOBAL_TIMEOUT`](crate::global::GLOBAL_TIMEOUT))
+/// elapses. Returns the value of the global.
+pub async fn inject_blob_with_global(content:Content<'_>, global : &str, timeout : Option<Duration>) -> Result<JsValue> {
+    inject_blob_with_cancel(content, timeout, &Cancel::new()).await?;
+    wait_for_global(&Target::default(), global, POLL_INTERVAL, timeout).await
+}
+
+/// Inject a [`Blob`](https://developer.mozilla.org/en-US/docs/Web/API/Blob)
+/// into DOM, setting the attributes supplied in [`Options`] on the
+/// injected element.
+pub async fn inject_blob_with_options(content:Content<'_>, options : &Options) -> Result<()> {
+    inject_blob_into(&Target::default(), content, options, None, &Cancel::new()).await
+}
+
+/// Inject a [`Blob`](https://developer.mozilla.org/en-US/docs/Web/API/Blob)
+/// into the `target` (a document, an element or a shadow root) with an
+/// optional `timeout` and a [`Cancel`] handle. Attributes supplied in
+/// `options` are set on the injected element. Fails with
+/// [`Error::Load`](crate::error::Error::Load) if the element dispatches
+/// the `error` event (e.g. a remote URL can not be loaded). The element
+/// of a failed injection is removed and its blob URL revoked.
+pub async fn inject_blob_into(target : &Target, content:Content<'_>, options : &Options, timeout : Option<Duration>, cancel : &Cancel) -> Result<()> {
+    let ident = content.ident();
+    let decompressed;
+    let content = match content {
+        Content::Compressed(compression, content) => {
+            decompressed = compression.decompress(content.data()?).await?;
+            content.with_data(&decompressed)?
+        },
+        content => content
+    };
     let (sender, receiver) = oneshot();
+    let error_sender = sender.clone();
     let callback = callback!(move |event : web_sys::CustomEvent| {
         sender.try_send(event).expect("inject_blob_with_callback(): unable to send load notification");
     });
-    inject_blob_with_callback(content,Some(&callback))?;
-    let _notification = receiver.recv().await?;
+    let error = callback!(move |event : web_sys::CustomEvent| {
+        error_sender.try_send(event).expect("inject_blob_with_callback(): unable to send error notification");
+    });
+    let element = inject_blob_into_with_callback(target,content,options,Some(&callback))?;
+    element.add_event_listener_with_callback("error", error.as_ref())?;
+    cancel.bind(&element)?;
+    let result = match cancel.wait(ident, &receiver, timeout).await {
+        Ok(notification) if notification.type_() == "error" => Err(Error::Load(ident.to_string(), notification.into())),
+        Ok(_) => Ok(()),
+        Err(err) => Err(err),
+    };
+    if result.is_err() {
+        // the blob URL is owned by the injected element
+        discard(&element)?;
+    }
+    result
+}
+
+/// Remove the `element` of a failed injection from DOM and revoke its blob URL
+fn discard(element : &Element) -> Result<()> {
+    element.remove();
+    let url = element.get_attribute("src").or_else(|| element.get_attribute("href"));
+    if let Some(url) = url.filter(|url| url.starts_with("blob:")) {
+        revoke_blob_url(&url)?;
+    }
     Ok(())
 }
 
 /// Inject script as a [`Blob`](https://developer.mozilla.org/en-US/docs/Web/API/Blob) buffer
 /// into DOM. Executes an optional `load` callback when the loading is complete. The load callback
 /// receives [`web_sys::CustomEvent`] struct indicating the load result.
+/// The blob URL of a classic script is revoked once loaded; module URLs
+/// remain live until revoked via [`revoke_blob_url`]. Source maps are
+/// handled according to the [`source_map`] configuration.
 // pub fn inject_script(root:Element, id : Option<&str>, content:&[u8], content_type:&str, callback : Option<&CustomEventCallback>) -> Result<()> {
-pub fn inject_script<C>(root:Element, id : Option<&str>, content:&[u8], content_type:&str, callback : Option<&C>) -> Result<()> 
+pub fn inject_script<C>(root:impl Into<Target>, id : Option<&str>, content:&[u8], content_type:&str, callback : Option<&C>) -> Result<Element> 
 where C : AsRef<Function>
 {
-    let doc = document();
-    let string = String::from_utf8_lossy(content);
-    let regex = regex::Regex::new(r"//# sourceMappingURL.*$").unwrap();
-    let content = regex.replace(&string, "");
+    inject_script_with_options(root, id, content, content_type, &Options::default(), callback)
+}
 
-    let args = Array::new_with_length(1);
-    args.set(0, unsafe { Uint8Array::view(content.as_bytes()).into() });
-    let mut options = web_sys::BlobPropertyBag::new();
-    options.type_("application/javascript");
-    let blob = Blob::new_with_u8_array_sequence_and_options(&args, &options)?;
-    let url = Url::create_object_url_with_blob(&blob)?;
+/// Inject script into DOM, setting the attributes supplied in [`Options`]
+/// (such as `crossorigin` or `async`) on the `<script>` element.
+pub fn inject_script_with_options<C>(root:impl Into<Target>, id : Option<&str>, content:&[u8], content_type:&str, options : &Options, callback : Option<&C>) -> Result<Element> 
+where C : AsRef<Function>
+{
+    let root = root.into();
+    let doc = root.document()?;
+    let string = String::from_utf8_lossy(content);
+    let mut map_url = None;
+    let content = source_map::process(&string, id.unwrap_or("blob"), None, 0, &mut map_url)?;
 
     let script = doc.create_element("script")?;
+    // modules may be imported by their URL, so only
+    // URLs of classic scripts are revoked once loaded
+    if let Some(map_url) = map_url.filter(|_| content_type != "module") {
+        revoke_on_load(&script, &map_url)?;
+    }
     if let Some(callback) = callback {
         script.add_event_listener_with_callback("load", callback.as_ref())?;
     }
@@ -88,21 +242,59 @@ where C : AsRef<Function>
         script.set_attribute("id", id)?;
     }
     script.set_attribute("type",content_type)?;
-    script.set_attribute("src", &url)?;
-    root.append_child(&script)?;
+    options.apply(&script)?;
 
-    Ok(())
+    if csp::is_inline() {
+        csp::apply_nonce(&script)?;
+        trusted_types::set_script_text(&script, &content)?;
+        root.append(&script)?;
+        csp::dispatch_load(&script)?;
+    } else {
+        let url = create_blob_url(content.as_bytes(), Some("application/javascript"))?;
+        if content_type != "module" {
+            revoke_on_load(&script, &url)?;
+        }
+        csp::apply(&script, content.as_bytes())?;
+        trusted_types::set_script_src(&script, &url)?;
+        root.append(&script)?;
+    }
+
+    Ok(script)
+}
+
+pub fn inject_stylesheet<C>(root: impl Into<Target>, id : Option<&str>, content: &[u8], callback: Option<&C>) -> Result<Element> 
+where C : AsRef<Function>
+{
+    inject_stylesheet_with_options(root, id, content, &Options::default(), callback)
 }
 
-pub fn inject_stylesheet<C>(root: Element, id : Option<&str>, content: &[u8], callback: Option<&C>) -> Result<()> 
+/// Inject a stylesheet into DOM, setting the attributes supplied in
+/// [`Options`] (such as `media` or `title`) on the created element.
+pub fn inject_stylesheet_with_options<C>(root: impl Into<Target>, id : Option<&str>, content: &[u8], options : &Options, callback: Option<&C>) -> Result<Element> 
 where C : AsRef<Function>
 {
-    let args = Array::new_with_length(1);
-    args.set(0, unsafe { Uint8Array::view(content).into() });
-    let blob = Blob::new_with_u8_array_sequence(&args)?;
-    let url = Url::create_object_url_with_blob(&blob)?;
+    let root = root.into();
+
+    if csp::is_inline() {
+        let style = root.document()?.create_element("style")?;
+        if let Some(callback) = callback {
+            style.add_event_listener_with_callback("load", callback.as_ref())?;
+        }
+        if let Some(id) = id {
+            style.set_attribute("id", id)?;
+        }
+        csp::apply_nonce(&style)?;
+        options.apply(&style)?;
+        style.set_text_content(Some(&String::from_utf8_lossy(content)));
+        root.append(&style)?;
+        return Ok(style);
+    }
 
-    let style = document().create_element("link")?;
+    let url = create_blob_url(content, None)?;
+
+    let style = root.document()?.create_element("link")?;
+    revoke_on_load(&style, &url)?;
+    csp::apply(&style, content)?;
     if let Some(callback) = callback {
         style.add_event_listener_with_callback("load", callback.as_ref())?;
         // closure.forget();
@@ -113,39 +305,105 @@ where C : AsRef<Function>
     style.set_attribute("type","text/css")?;
     style.set_attribute("rel","stylesheet")?;
     style.set_attribute("href",&url)?;
-    root.append_child(&style)?;
-    Ok(())
+    options.apply(&style)?;
+    root.append(&style)?;
+    Ok(style)
+}
+
+/// Inject a `<script>` element referring to a remote `url` into DOM.
+/// Executes an optional `load` callback when the loading is complete.
+pub fn inject_script_url<C>(root:impl Into<Target>, id : Option<&str>, url:&str, content_type:&str, options : &Options, callback : Option<&C>) -> Result<Element> 
+where C : AsRef<Function>
+{
+    let root = root.into();
+    let script = root.document()?.create_element("script")?;
+    if let Some(callback) = callback {
+        script.add_event_listener_with_callback("load", callback.as_ref())?;
+    }
+    if let Some(id) = id {
+        script.set_attribute("id", id)?;
+    }
+    script.set_attribute("type",content_type)?;
+    options.apply(&script)?;
+    csp::apply_nonce(&script)?;
+    trusted_types::set_script_src(&script, url)?;
+    root.append(&script)?;
+    Ok(script)
+}
+
+/// Inject a `<link>` element referring to a remote stylesheet `url` into DOM.
+/// Executes an optional `load` callback when the loading is complete.
+pub fn inject_stylesheet_url<C>(root:impl Into<Target>, id : Option<&str>, url:&str, options : &Options, callback : Option<&C>) -> Result<Element> 
+where C : AsRef<Function>
+{
+    let root = root.into();
+    let style = root.document()?.create_element("link")?;
+    if let Some(callback) = callback {
+        style.add_event_listener_with_callback("load", callback.as_ref())?;
+    }
+    if let Some(id) = id {
+        style.set_attribute("id", id)?;
+    }
+    style.set_attribute("type","text/css")?;
+    style.set_attribute("rel","stylesheet")?;
+    style.set_attribute("href",url)?;
+    options.apply(&style)?;
+    csp::apply_nonce(&style)?;
+    root.append(&style)?;
+    Ok(style)
 }
 
 /// Inject data buffer contained in the [`Content`] struct as a [`Blob`](https://developer.mozilla.org/en-US/docs/Web/API/Blob)
 /// into DOM. Executes an optional `load` callback when the loading is complete. The load callback
 /// receives [`web_sys::CustomEvent`] struct indicating the load result.
-pub fn inject_blob_with_callback<C>(content : Content, callback: Option<&C>) -> Result<()>
+/// Returns the injected element.
+pub fn inject_blob_with_callback<C>(content : Content, callback: Option<&C>) -> Result<Element>
 // pub fn inject_blob_with_callback(content : Content, callback : Option<&CustomEventCallback>) -> Result<()> 
 where C : AsRef<Function>
 {
+    inject_blob_into_with_callback(&Target::default(), content, &Options::default(), callback)
+}
 
-    let doc = document();
-    let root = {
-        let collection = doc.get_elements_by_tag_name("head");
-        if collection.length() > 0 {
-            collection.item(0).unwrap()
-        } else {
-            doc.get_elements_by_tag_name("body").item(0).unwrap()
-        }
-    };
+/// Inject data buffer contained in the [`Content`] struct into the `target`
+/// (a document, an element or a shadow root). Executes an optional `load` callback
+/// when the loading is complete. Attributes supplied in `options` are set on
+/// the injected element. Returns the injected element.
+pub fn inject_blob_into_with_callback<C>(target : &Target, content : Content, options : &Options, callback: Option<&C>) -> Result<Element>
+where C : AsRef<Function>
+{
+    let root = target.clone();
 
-    match content {
+    let element = match content {
         Content::Script(id, content) => {
-            inject_script(root, id, content, "text/javascript", callback)?;
+            inject_script_with_options(root, id, content, "text/javascript", options, callback)?
         },
         Content::Module(id, content) => {
-            inject_script(root, id, content, "module", callback)?;
+            inject_script_with_options(root, id, content, "module", options, callback)?
         },
         Content::Style(id, content) => {
-            inject_stylesheet(root, id, content, callback)?;
+            inject_stylesheet_with_options(root, id, content, options, callback)?
         },
-    }
+        Content::ScriptUrl(id, url) => {
+            inject_script_url(root, id, url, "text/javascript", options, callback)?
+        },
+        Content::ModuleUrl(id, url) => {
+            inject_script_url(root, id, url, "module", options, callback)?
+        },
+        Content::StyleUrl(id, url) => {
+            inject_stylesheet_url(root, id, url, options, callback)?
+        },
+        // `DecompressionStream` is asynchronous, so synchronous
+        // injection requires the Rust implementation
+        #[cfg(feature = "compression-fallback")]
+        Content::Compressed(compression, content) => {
+            let data = compression.decompress_sync(content.data()?)?;
+            inject_blob_into_with_callback(target, content.with_data(&data)?, options, callback)?
+        },
+        #[cfg(not(feature = "compression-fallback"))]
+        Content::Compressed(compression, _) => {
+            return Err(format!("unable to inject {} content synchronously: enable the `compression-fallback` feature or use `inject_blob_into()`", compression.format()).into());
+        },
+    };
 
-    Ok(())
+    Ok(element)
 }
\ No newline at end of file
@@ -18,6 +18,21 @@
 
 pub mod inject;
 pub mod loader;
+pub mod cancel;
+pub mod blob;
+pub mod target;
+pub mod stylesheet;
+pub mod fetch;
+pub mod compression;
+pub mod source_map;
+pub mod import_map;
+pub mod asset;
+pub mod import;
+pub mod global;
+pub mod worker;
+pub mod csp;
+pub mod options;
+pub mod trusted_types;
 pub mod utils;
 pub mod error;
 pub mod result;
\ No newline at end of file
@@ -1,17 +1,37 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
 use std::sync::Arc;
-use std::sync::Mutex;
+use std::sync::{Mutex, OnceLock};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
 use workflow_log::*;
 use workflow_wasm::callback::*;
-use js_sys::{Array,Uint8Array};
-use web_sys::{Document,Url,Blob};
-use workflow_core::channel::oneshot;
-use futures::future::{join_all,BoxFuture,FutureExt};
+use web_sys::{Document,Element};
+use workflow_core::channel::{oneshot,unbounded,Sender,Receiver};
+use futures::future::{join,join_all,BoxFuture,FutureExt};
 use workflow_core::lookup::*;
 use crate::error::Error;
 use crate::result::Result;
+use crate::cancel::Cancel;
+use crate::blob::{create_blob_url, revoke_blob_url};
+use crate::utils::head_or_body;
+use crate::target::Target;
+use crate::stylesheet::StyleSheet;
+use crate::options::Options;
+use crate::fetch::Fetch;
+use crate::compression::Compression;
+use crate::csp;
+use crate::source_map;
+use crate::import_map::ImportMap;
+use crate::asset;
+use crate::worker::{Worker, WorkerKind};
+use crate::global::{wait_until_defined, POLL_INTERVAL};
+use crate::import::{self, ModuleNamespace};
+use crate::utils::{local, resource_size};
+use crate::trusted_types;
 use workflow_core::time::*;
+use regex::{Captures, Regex};
+pub use workflow_dom_macros::include_content;
 
 pub type Id = u64;
 pub type ContentMap = HashMap<Id,Arc<Content>>;
@@ -28,14 +48,7 @@ pub fn root() -> web_sys::Element {
         match DOCUMENT_ROOT.as_ref() {
             Some(root) => root.clone(),
             None => {
-                let root = {
-                    let collection = document().get_elements_by_tag_name("head");
-                    if collection.length() > 0 {
-                        collection.item(0).unwrap()
-                    } else {
-                        document().get_elements_by_tag_name("body").item(0).unwrap()
-                    }
-                };
+                let root = head_or_body(&document()).unwrap();
                 DOCUMENT_ROOT = Some(root.clone());
                 root
             }
@@ -48,6 +61,9 @@ pub enum ContentType {
     Module,
     Script,
     Style,
+    /// Binary asset (such as a font or an image) backed by [`Content::data`];
+    /// loading an asset creates its blob URL without injecting it
+    Asset,
 }
 
 impl ContentType {
@@ -56,54 +72,147 @@ impl ContentType {
     }
 }
 
-#[allow(dead_code)]
+/// Dependency of [`Content`] on another declared content
 pub enum Reference {
+    /// Module import (`import ... from`); the referenced module is
+    /// loaded and imported by its URL
     Module,
+    /// Classic script that must be executed before the referencing content;
+    /// script references are loaded sequentially in the declared order
     Script,
+    /// Stylesheet that must be loaded and applied before
+    /// the referencing content is injected
     Style,
+    /// Module re-export (`export ... from`)
     Export,
+    /// Stylesheet imported by a CSS `@import` rule of the referencing
+    /// stylesheet; the rule is rewritten to the blob URL of the imported
+    /// stylesheet, which is not injected on its own
+    Import,
+    /// Content referred to by a CSS `url()` function of the referencing
+    /// stylesheet (such as a font or an image); the function is rewritten
+    /// to the blob URL of the referenced content
+    Url,
+}
+
+impl Reference {
+    /// Content type expected of the referenced content;
+    /// `None` if content of any type can be referenced
+    pub fn content_type(&self) -> Option<ContentType> {
+        match self {
+            Reference::Module | Reference::Export => Some(ContentType::Module),
+            Reference::Script => Some(ContentType::Script),
+            Reference::Style | Reference::Import => Some(ContentType::Style),
+            Reference::Url => None,
+        }
+    }
+
+    /// Returns `true` for references made by CSS `@import` rules and `url()` functions
+    pub fn is_css(&self) -> bool {
+        matches!(self, Reference::Import | Reference::Url)
+    }
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub enum ContentStatus {
     Loaded,
     Exists,
-    Error,
+    Error(Error),
 }
 
+/// Declared content. Content is immutable and may be declared by multiple
+/// contexts; its load state and blob URLs are kept by each [`Context`].
 pub struct Content {
     pub content_type : ContentType,
-    pub url : Mutex<Option<String>>,
     pub id : Id,
     pub ident : &'static str,
     pub content: &'static str,
     pub references: Option<&'static [(Reference, Option<&'static str>, Id)]>,
-    pub is_loaded : AtomicBool,
+    /// Data of a binary asset
+    pub data : &'static [u8],
+    /// MIME type of a binary asset
+    pub mime : Option<&'static str>,
+    pub options : Option<Options>,
+    /// Remote URL the content is loaded from instead of the embedded `content`
+    pub src : Option<&'static str>,
+    /// Source the content is fetched (and cached) from instead of
+    /// the embedded `content`; fetched content is injected as a blob
+    pub fetch : Option<Fetch>,
+    /// Compressed data decompressed and used instead of the embedded `content`
+    pub compressed : Option<(Compression, &'static [u8])>,
+    /// Source map of the script (see [`source_map`])
+    pub source_map : Option<&'static str>,
+    /// Dotted global path (e.g. `Chart`) defined by a classic script;
+    /// loading completes once the global is defined on the window of the
+    /// context target, failing after the context timeout (or
+    /// [`GLOBAL_TIMEOUT`](crate::global::GLOBAL_TIMEOUT))
+    pub global : Option<&'static str>,
 }
 
 // unsafe impl Send for Module {}
 // unsafe impl Sync for Module {}
 
+impl Default for Content {
+    fn default() -> Self {
+        Content {
+            content_type : ContentType::Module,
+            id : 0,
+            ident : "",
+            content : "",
+            references : None,
+            data : &[],
+            mime : None,
+            options : None,
+            src : None,
+            fetch : None,
+            compressed : None,
+            source_map : None,
+            global : None,
+        }
+    }
+}
+
 impl Content {
 
-    pub fn url(&self) -> Option<String> { 
-        self.url.lock().unwrap().clone() 
+    /// Returns the blob URL of the content within `ctx` or its remote `src` URL
+    fn url(&self, ctx : &Context) -> Option<String> {
+        ctx.state(&self.id, |state| state.url.clone()).or_else(|| self.src.map(String::from))
     }
 
     // fn content(&self, ctx: &Context) -> Result<String> {
     fn content(&self, ctx: &Context) -> Result<String> {
+        self.content_with(ctx, self.content)
+    }
+
+    /// Content text composed of the module imports, the `body` and the module exports.
+    /// Imports are placed on separate lines preceding the `body`, and source maps
+    /// of scripts are offset accordingly.
+    fn content_with(&self, ctx: &Context, body : &str) -> Result<String> {
+        let (lines, text) = self.compose(ctx, body)?;
+        if self.content_type.is_js() {
+            ctx.state(&self.id, |state| source_map::process(&text, self.ident, self.source_map, lines, &mut state.map_url))
+        } else {
+            Ok(text)
+        }
+    }
+
+    /// Returns the number of import lines and the composed text
+    fn compose(&self, ctx: &Context, body : &str) -> Result<(usize, String)> {
         let mut text = String::new();
 
+        if let (ContentType::Style, Some(references)) = (&self.content_type, &self.references) {
+            return Ok((0, self.rewrite_css(ctx, references, body)?));
+        }
+
         if let Some(references) = &self.references {
             let mut imports = Vec::new();
             let mut exports = Vec::new();
 
             for (kind,what,id) in references.iter() {
-                let module = ctx.get(id).ok_or(format!("unable to lookup module `{}`",self.ident))?;
-                let url = module.url().ok_or(format!("[{}] module is not loaded `{}`",self.ident,id))?;
                 match kind {
                     Reference::Module => {
+                        let module = ctx.get(id).ok_or(format!("unable to lookup module `{}`",self.ident))?;
+                        let url = module.url(ctx).ok_or(format!("[{}] module is not loaded `{}`",self.ident,id))?;
                         match what {
                             Some(detail) => {
                                 imports.push(format!("import {} from \"{}\";", detail, url));
@@ -115,125 +224,433 @@ impl Content {
                     },
                     Reference::Export => {
                         let module = ctx.get(id).ok_or(format!("unable to lookup module `{}`",self.ident))?;
-                        let url = module.url().ok_or(format!("[{}] module is not loaded `{}`",self.ident,id))?;
+                        let url = module.url(ctx).ok_or(format!("[{}] module is not loaded `{}`",self.ident,id))?;
                         exports.push(format!("export {} from \"{}\";", what.unwrap(), url));
                     },
-                    _ => { }
+                    // scripts and stylesheets are loaded by load_deps()
+                    // prior to injection and are not imported
+                    Reference::Script | Reference::Style => { },
+                    // validated as references made by stylesheets only
+                    Reference::Import | Reference::Url => { }
                 }
             }
 
-            let imports = imports.join("\n");
+            let lines = imports.len();
             let exports = exports.join("\n");
 
-            text += &imports;
-            text += &self.content;
-            text += &exports;
-            Ok(text)
+            for import in imports {
+                text += &import;
+                text += "\n";
+            }
+            text += body;
+            if !exports.is_empty() {
+                text += "\n";
+                text += &exports;
+            }
+            Ok((lines, text))
         } else {
-            Ok(self.content.to_string())
+            Ok((0, body.to_string()))
         }
 
     }
 
-    pub fn is_loaded(&self) -> bool {
-        self.is_loaded.load(Ordering::SeqCst)
+    /// Rewrite CSS `@import` rules and `url()` functions of the stylesheet `body`
+    /// referring to declared content to blob URLs of that content. References
+    /// to content that has not been declared are left unchanged.
+    fn rewrite_css(&self, ctx: &Context, references : &[(Reference, Option<&'static str>, Id)], body : &str) -> Result<String> {
+        let mut error = None;
+        let text = css_reference().replace_all(body, |captures : &Captures| {
+            let (spec, import) = match captures.name("import").or_else(|| captures.name("quoted")) {
+                Some(spec) => (spec.as_str(), true),
+                None => (&captures["url"], false),
+            };
+            let reference = references.iter().find(|(kind,what,_)| kind.is_css() && *what == Some(spec));
+            let id = match reference {
+                Some((_,_,id)) => id,
+                None => return captures[0].to_string(),
+            };
+            match ctx.get(id).and_then(|content| content.url(ctx)) {
+                Some(url) if import => format!("@import url(\"{}\")", url),
+                Some(url) => format!("url(\"{}\")", url),
+                None => {
+                    error.get_or_insert_with(|| format!("[{}] referenced content is not available `{}`", self.ident, id));
+                    captures[0].to_string()
+                }
+            }
+        });
+        match error {
+            Some(error) => Err(error.into()),
+            None => Ok(text.to_string())
+        }
+    }
+
+    /// Returns the URL of this script for use within a worker, creating blob
+    /// URLs of the script and of the modules it imports without injecting them
+    fn worker_url(self : Arc<Self>, ctx: Arc<Context>) -> BoxFuture<'static, Result<String>> {
+        async move {
+            if let Some(url) = self.url(&ctx) {
+                return Ok(url);
+            }
+            if let Some(references) = self.references {
+                for (kind,_,id) in references.iter() {
+                    match kind.content_type() {
+                        Some(ContentType::Module) => {
+                            let content = ctx.get(id).ok_or(Error::NotFound(*id))?;
+                            content.worker_url(ctx.clone()).await?;
+                        },
+                        _ => {
+                            let msg = "only module imports are supported by workers".to_string();
+                            return Err(Error::ReferenceType(self.ident.to_string(), id.to_string(), msg));
+                        }
+                    }
+                }
+            }
+            let text = self.text(&ctx).await?;
+            match self.url(&ctx) {
+                Some(url) => Ok(url),
+                None => self.create_blob_url(&ctx, &text)
+            }
+        }.boxed()
+    }
+
+    /// Returns `true` if the stylesheet imports other stylesheets
+    fn has_imports(&self) -> bool {
+        self.references
+            .map(|references| references.iter().any(|(kind,_,_)| matches!(kind, Reference::Import)))
+            .unwrap_or(false)
+    }
+
+    /// Returns the URL of this content, creating a blob URL without injecting
+    /// the content if needed. Used for content referenced by stylesheets,
+    /// the URLs of which remain live until revoked.
+    fn ensure_url(self : Arc<Self>, ctx: Arc<Context>) -> BoxFuture<'static, Result<String>> {
+        async move {
+            if self.content_type == ContentType::Asset {
+                return self.asset_url(&ctx).await;
+            }
+            if let Some(url) = self.url(&ctx) {
+                return Ok(url);
+            }
+            self.clone().load_deps(ctx.clone()).await?;
+            let text = self.text(&ctx).await?;
+            match self.url(&ctx) {
+                Some(url) => Ok(url),
+                None => self.create_blob_url(&ctx, &text)
+            }
+        }.boxed()
     }
 
     fn load_deps(self : Arc<Self>, ctx: Arc<Context>) -> BoxFuture<'static, Result<()>> {
 
         async move {
             if let Some(references) = &self.references {
-                let futures = references
-                    .iter()
-                    .map(|(_,_,id)| {
-                        if let Some(content) = ctx.get(id) {
-                            if !content.is_loaded.load(Ordering::SeqCst) {
-                                Some(content.load(&ctx))
+                let strict = ctx.is_strict();
+                let mut scripts = Vec::new();
+                let mut futures = Vec::new();
+                for (kind,_,id) in references.iter() {
+                    match ctx.get(id) {
+                        // content referenced by stylesheets only requires a URL
+                        Some(content) if kind.is_css() => {
+                            let ident = content.ident;
+                            let result = content.ensure_url(ctx.clone()).map(|result| result.map(|_| ContentStatus::Loaded));
+                            futures.push(result.map(move |result| (ident, result)).boxed());
+                        },
+                        Some(content) if !ctx.is_loaded(&content.id) => {
+                            if let Reference::Script = kind {
+                                scripts.push(content);
                             } else {
-                                None
+                                let ident = content.ident;
+                                futures.push(content.load(&ctx).map(move |result| (ident, result)).boxed());
                             }
-                        } else {
+                        },
+                        Some(_) => { },
+                        None if strict => {
+                            return Err(self.dependency_failed(&id.to_string(), Error::NotFound(*id)));
+                        },
+                        None => {
                             log_error!("Unable to locate module {}", id);
-                            None
                         }
-                    })
-                    .flatten()
-                    .collect::<Vec<_>>();
-                
-                join_all(futures).await;
+                    }
+                }
+
+                // classic scripts are executed in the declared order
+                let scripts = async {
+                    let mut results = Vec::new();
+                    for content in scripts {
+                        let ident = content.ident;
+                        let result = content.load(&ctx).await;
+                        let failed = result.is_err();
+                        results.push((ident, result));
+                        if failed && strict {
+                            break;
+                        }
+                    }
+                    results
+                };
 
-                // for future in futures {
-                //     future.await?;
-                // }
+                let (mut results, scripts) = join(join_all(futures), scripts).await;
+                results.extend(scripts);
+                for (ident, result) in results {
+                    if let Err(err) = result {
+                        if strict {
+                            return Err(self.dependency_failed(ident, err));
+                        } else {
+                            log_error!("{}", err);
+                        }
+                    }
+                }
             }
             Ok(())
         }.boxed()
     }
 
+    fn dependency_failed(&self, dependency : &str, err : Error) -> Error {
+        Error::DependencyFailed {
+            ident : self.ident.to_string(),
+            dependency : dependency.to_string(),
+            source : Box::new(err),
+        }
+    }
+
     pub async fn load(self : Arc<Self>, ctx: &Arc<Context>) -> Result<ContentStatus> {
         ctx.load_content(self).await
     }
 
-    fn create_blob_url(&self, ctx: &Arc<Context>) -> Result<String> {
+    fn create_blob_url(&self, ctx : &Context, content : &str) -> Result<String> {
 
-        let content = self.content(ctx)?;
-        let args = Array::new_with_length(1);
-        args.set(0, unsafe { Uint8Array::view(content.as_bytes()).into() });
-        let mut options = web_sys::BlobPropertyBag::new();
-        match self.content_type {
-            ContentType::Module | ContentType::Script => {
-                options.type_("application/javascript");
-            },
-            ContentType::Style => {
-                options.type_("text/css");
-            },
+        let content_type = match self.content_type {
+            ContentType::Module | ContentType::Script => "application/javascript",
+            ContentType::Style => "text/css",
+            ContentType::Asset => self.mime.unwrap_or("application/octet-stream"),
+        };
+
+        let url = create_blob_url(content.as_bytes(), Some(content_type))?;
+        if let Some(previous) = ctx.state(&self.id, |state| state.url.replace(url.clone())) {
+            revoke_blob_url(&previous)?;
         }
+        Ok(url)
+    }
 
-        let blob = Blob::new_with_u8_array_sequence_and_options(&args, &options)?;
-        let url = Url::create_object_url_with_blob(&blob)?;
-        self.url.lock().unwrap().replace(url.clone());
+    /// Size of the content source in bytes (see [`Context::size`])
+    fn size(&self, ctx : &Context) -> usize {
+        let size = ctx.state(&self.id, |state| state.size);
+        if size > 0 || self.fetch.is_some() || self.src.is_some() {
+            return size;
+        }
+        match (&self.compressed, &self.content_type) {
+            (Some((_, data)), _) => data.len(),
+            (None, ContentType::Asset) => self.data.len(),
+            (None, _) => self.content.len(),
+        }
+    }
+
+    /// Create the blob URL of a binary asset from its (fetched,
+    /// decompressed or embedded) data, unless the URL exists
+    async fn asset_url(&self, ctx : &Context) -> Result<String> {
+        if let Some(url) = self.url(ctx) {
+            return Ok(url);
+        }
+        let data = match (&self.fetch, &self.compressed) {
+            (Some(fetch), _) => fetch.bytes().await?,
+            (None, Some((compression, data))) => compression.decompress(data).await?,
+            (None, None) => self.data.to_vec(),
+        };
+        let url = create_blob_url(&data, Some(self.mime.unwrap_or("application/octet-stream")))?;
+        ctx.state(&self.id, |state| {
+            state.size = data.len();
+            state.url.replace(url.clone());
+        });
         Ok(url)
     }
 
-    async fn load_impl(self : &Arc<Self>, ctx: &Arc<Context>) -> Result<ContentStatus> {
+    /// Returns `true` if the content is loaded from a remote URL
+    pub fn is_remote(&self) -> bool {
+        self.src.is_some()
+    }
+
+    /// Returns the content text, fetching the content if it is declared
+    /// with a [`Fetch`] source or decompressing compressed content. The
+    /// fetched or decompressed body is retrieved once and kept by `ctx`.
+    async fn text(&self, ctx : &Context) -> Result<String> {
+        if self.fetch.is_none() && self.compressed.is_none() {
+            return self.content(ctx);
+        }
+        let body = match ctx.state(&self.id, |state| state.body.clone()) {
+            Some(body) => body,
+            None => {
+                let body : Arc<str> = match (&self.fetch, &self.compressed) {
+                    (Some(fetch), _) => fetch.text().await?.into(),
+                    (None, Some((compression, data))) => String::from_utf8_lossy(&compression.decompress(data).await?).into(),
+                    (None, None) => unreachable!(),
+                };
+                ctx.state(&self.id, |state| {
+                    state.size = body.len();
+                    state.body.replace(body.clone());
+                });
+                body
+            }
+        };
+        self.content_with(ctx, &body)
+    }
+
+    /// Text and URL of the element injected for this content. Remote content
+    /// is referred to by its `src` URL, while embedded and fetched content is
+    /// injected as a blob URL or as inline text (see [`csp::Policy::inline`]).
+    async fn prepare(&self, ctx : &Context) -> Result<(String, Option<String>)> {
+        if let Some(src) = self.src {
+            return Ok((String::new(), Some(src.to_string())));
+        }
+        let text = self.text(ctx).await?;
+        // an existing blob URL (e.g. of a module listed in the import map
+        // or of a stylesheet referenced by `@import` rules) is reused
+        if let Some(url) = ctx.state(&self.id, |state| state.url.clone()) {
+            return Ok((text, Some(url)));
+        }
+        let url = if csp::is_inline() { None } else { Some(self.create_blob_url(ctx, &text)?) };
+        Ok((text, url))
+    }
+
+    /// Revoke the blob URL of this content within `ctx` (and of its
+    /// source map). Returns `false` if the content has no live URL.
+    fn revoke(&self, ctx : &Context) -> Result<bool> {
+        let (url, map_url) = ctx.state(&self.id, |state| (state.url.take(), state.map_url.take()));
+        revoke_urls(url, map_url)
+    }
+
+    /// Returns `true` if this content references content `id`
+    pub fn references(&self, id : &Id) -> bool {
+        self.references
+            .map(|references| references.iter().any(|(_,_,reference)| reference == id))
+            .unwrap_or(false)
+    }
+
+    async fn load_impl(self : &Arc<Self>, ctx: &Arc<Context>, timeout : Option<Duration>) -> Result<ContentStatus> {
 
-        if self.is_loaded() {
+        if ctx.is_loaded(&self.id) {
             return Ok(ContentStatus::Exists);
         }
+
+        // assets are referenced by their URL and are not injected
+        if self.content_type == ContentType::Asset {
+            self.asset_url(ctx).await?;
+            ctx.state(&self.id, |state| state.loaded = true);
+            return Ok(ContentStatus::Loaded);
+        }
         
         self.clone().load_deps(ctx.clone()).await?;
         // log_info!("load ... {}", self.ident);
-        
+
+        // the timeout covers retrieval of fetched content and its injection
+        let cancel = Cancel::new();
+        ctx.pending.lock().unwrap().insert(self.id, cancel.clone());
+        let start = Instant::now();
+        let result = match cancel.until(self.ident, self.prepare(ctx), timeout).await {
+            Ok((text, url)) => {
+                let timeout = timeout.map(|timeout| timeout.saturating_sub(start.elapsed()));
+                match self.inject(ctx, &ctx.target, &text, url.as_deref(), timeout, &cancel).await {
+                    // scripts may define their global after the `load` event
+                    Ok(status) => match self.global {
+                        Some(global) => {
+                            let defined = wait_until_defined(&ctx.target, global, POLL_INTERVAL, timeout);
+                            cancel.until(self.ident, defined, None).await.map(|_| status)
+                        },
+                        None => Ok(status)
+                    },
+                    Err(err) => Err(err)
+                }
+            },
+            Err(err) => Err(err)
+        };
+        ctx.pending.lock().unwrap().remove(&self.id);
+
+        match result {
+            Ok(status) => {
+                ctx.state(&self.id, |state| {
+                    state.loaded = true;
+                    if let Some(src) = self.src {
+                        state.size = resource_size(src);
+                    }
+                });
+                // modules are imported by their URL from dependents, while
+                // classic scripts and stylesheets are no longer needed once loaded
+                if self.content_type != ContentType::Module {
+                    self.revoke(ctx)?;
+                }
+                Ok(status)
+            },
+            Err(err) => {
+                // the element of the failed content is discarded;
+                // URLs of modules listed in the import map are retained
+                cancel.remove_element();
+                if !ctx.is_mapped(&self.id) {
+                    self.revoke(ctx)?;
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Inject an element referring to `url` into the `target` and wait
+    /// for it to load. If `url` is `None`, `text` is injected inline.
+    async fn inject(&self, ctx : &Context, target : &Target, text : &str, url : Option<&str>, timeout : Option<Duration>, cancel : &Cancel) -> Result<ContentStatus> {
         let (sender,receiver) = oneshot();
-        let url = self.create_blob_url(ctx)?;
-
-            // let ident = self.ident.clone();
-        let callback = callback!(move |_event: web_sys::CustomEvent| {
-            // log_info!("{} ... done", ident);
-            // TODO - analyze event
-            let status = ContentStatus::Loaded;
-            sender.try_send(status).expect("unable to post load event");
+
+        let load_sender = sender.clone();
+        let load = callback!(move |_event: web_sys::CustomEvent| {
+            load_sender.try_send(ContentStatus::Loaded).expect("unable to post load event");
+        });
+
+        let ident = self.ident;
+        let error = callback!(move |event: web_sys::CustomEvent| {
+            let err = Error::Load(ident.to_string(), event.into());
+            sender.try_send(ContentStatus::Error(err)).expect("unable to post error event");
         });
 
+        let start = Instant::now();
         match &self.content_type {
             ContentType::Module | ContentType::Script => {
-                self.inject_script(&url, &callback)?;
+                cancel.bind(&self.inject_script(target, text, url, &load, &error)?)?;
             },
             ContentType::Style => {
-                self.inject_style(&url, &callback)?;
+                cancel.bind(&self.inject_style(target, text, url, &load, &error)?)?;
+            },
+            ContentType::Asset => {
+                return Err(format!("unable to inject `{}`: assets are not injected", self.ident).into());
             }
         };
-        let status = receiver.recv().await.expect("unable to recv() load event");
-        self.is_loaded.store(true, Ordering::SeqCst);
-        Ok(status)
+        ctx.notify(LoadEvent::Injected { id : self.id, ident : self.ident, size : self.size(ctx), elapsed : start.elapsed() });
+
+        match cancel.wait(self.ident, &receiver, timeout).await? {
+            ContentStatus::Error(err) => Err(err),
+            status => Ok(status)
+        }
+    }
+
+    /// Set the attributes supplied in [`Content::options`] on the `element`
+    fn apply_options(&self, element : &Element) -> Result<()> {
+        if let Some(options) = &self.options {
+            options.apply(element)?;
+        }
+        Ok(())
+    }
+
+    /// Apply the CSP attributes to the `element`. Integrity of remote
+    /// content is not known, so only the `nonce` is applied.
+    fn apply_csp(&self, element : &Element, text : &str) -> Result<()> {
+        if self.is_remote() {
+            csp::apply_nonce(element)
+        } else {
+            csp::apply(element, text.as_bytes())
+        }
     }
 
-    fn inject_script<C>(&self, url : &str, callback : &C) -> Result<()>
+    fn inject_script<C>(&self, target : &Target, text : &str, url : Option<&str>, load : &C, error : &C) -> Result<Element>
     where
         C: AsRef<js_sys::Function>
     {
-        let script = document().create_element("script")?;                    
-        script.add_event_listener_with_callback("load", callback.as_ref())?;
+        let script = target.document()?.create_element("script")?;                    
+        script.add_event_listener_with_callback("load", load.as_ref())?;
+        script.add_event_listener_with_callback("error", error.as_ref())?;
 
         match &self.content_type {
             ContentType::Module => {
@@ -245,25 +662,55 @@ impl Content {
             },
             _ => { panic!("inject_script() unsupported content type `{:?}`", self.content_type) }
         }
-        script.set_attribute("src", url)?;
         script.set_attribute("id", self.ident)?;
-        root().append_child(&script)?;
-        Ok(())
+        self.apply_options(&script)?;
+        match url {
+            Some(url) => {
+                self.apply_csp(&script, text)?;
+                trusted_types::set_script_src(&script, url)?;
+                target.append(&script)?;
+            },
+            None => {
+                csp::apply_nonce(&script)?;
+                trusted_types::set_script_text(&script, text)?;
+                target.append(&script)?;
+                csp::dispatch_load(&script)?;
+            }
+        }
+        Ok(script)
     }
 
-    fn inject_style<C>(&self, url : &str, callback : &C) -> Result<()>
+    fn inject_style<C>(&self, target : &Target, text : &str, url : Option<&str>, load : &C, error : &C) -> Result<Element>
     where
         C: AsRef<js_sys::Function>
     {
-        let style = document().create_element("link")?;
-        style.add_event_listener_with_callback("load", callback.as_ref())?;
+        let url = match url {
+            Some(url) => url,
+            None => {
+                let style = target.document()?.create_element("style")?;
+                // inline styles dispatch `load` (or `error` if an `@import` fails)
+                style.add_event_listener_with_callback("load", load.as_ref())?;
+                style.add_event_listener_with_callback("error", error.as_ref())?;
+                style.set_attribute("id",self.ident)?;
+                self.apply_options(&style)?;
+                csp::apply_nonce(&style)?;
+                style.set_text_content(Some(text));
+                target.append(&style)?;
+                return Ok(style);
+            }
+        };
+
+        let style = target.document()?.create_element("link")?;
+        self.apply_csp(&style, text)?;
+        style.add_event_listener_with_callback("load", load.as_ref())?;
+        style.add_event_listener_with_callback("error", error.as_ref())?;
         style.set_attribute("type","text/css")?;
         style.set_attribute("rel","stylesheet")?;
-        style.set_attribute("href",&url)?;
+        style.set_attribute("href",url)?;
         style.set_attribute("id",self.ident)?;
-        root().append_child(&style)?;
-println!("injecting style `{}`", self.ident);
-        Ok(())
+        self.apply_options(&style)?;
+        target.append(&style)?;
+        Ok(style)
     }
 
 }
@@ -273,19 +720,112 @@ pub struct Context {
     pub content : Arc<Mutex<ContentMap>>,
     pub lookup_handler : LookupHandler<Id,ContentStatus,Error>,
     pub loaded : AtomicUsize,
+    pub timeout : Mutex<Option<Duration>>,
+    pub strict : AtomicBool,
+    pending : Mutex<HashMap<Id,Cancel>>,
+    errors : Mutex<HashMap<Id,Error>>,
+    observers : Mutex<Vec<Sender<LoadEvent>>>,
+    /// load state of declared content
+    state : Mutex<HashMap<Id,State>>,
+    /// shared stylesheets along with the targets adopting them
+    sheets : Mutex<HashMap<Id,(StyleSheet,Vec<Target>)>>,
+    /// targets of stylesheets attached as `<link>` elements
+    links : Mutex<HashMap<Id,Vec<Target>>>,
+    mapped : Mutex<HashSet<Id>>,
+    document : Document,
+    target : Target,
+}
+
+unsafe impl Send for Context {}
+unsafe impl Sync for Context {}
+
+/// Load state of content within a [`Context`]
+#[derive(Default)]
+struct State {
+    loaded : bool,
+    /// Blob URL of the content (remote content is referred to by its `src`)
+    url : Option<String>,
+    /// Blob URL of the source map (see [`source_map::Mode::Blob`]),
+    /// revoked along with the content URL
+    map_url : Option<String>,
+    /// Size in bytes of fetched, decompressed or remote content,
+    /// known once the content has been retrieved
+    size : usize,
+    /// Fetched or decompressed body of the content
+    body : Option<Arc<str>>,
+}
+
+/// Load progress event posted to [`Context::subscribe`] receivers.
+/// `size` is the size of the content source in bytes (see [`Context::size`])
+/// and `elapsed` is the time passed since the content load has started.
+#[derive(Debug, Clone)]
+pub enum LoadEvent {
+    /// Content load has started (dependencies are loaded first)
+    Started { id : Id, ident : &'static str, size : usize },
+    /// Content element has been injected into DOM
+    Injected { id : Id, ident : &'static str, size : usize, elapsed : Duration },
+    /// Content has loaded
+    Loaded { id : Id, ident : &'static str, size : usize, elapsed : Duration },
+    /// Content has failed to load
+    Failed { id : Id, ident : &'static str, size : usize, elapsed : Duration, error : Error },
+}
+
+/// Result of [`Context::load_ids`]
+#[derive(Debug, Clone)]
+pub struct LoadReport {
+    /// Load status of each requested id
+    pub status : Vec<(Id, ContentStatus)>,
+    /// Requested content and dependencies that failed to load
+    pub failed : Vec<(Id, Error)>,
+    /// Total time taken by the load
+    pub elapsed : Duration,
+}
+
+impl LoadReport {
+    /// Returns `true` if all requested content and dependencies have loaded
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty() && self.status.iter().all(|(_,status)| !matches!(status, ContentStatus::Error(_)))
+    }
 }
 
 impl Default for Context {
     fn default() -> Self {
-        Context {
+        Context::new(root()).expect("Unable to create the loader context")
+    }
+}
+
+impl Context {
+
+    /// Create a context injecting content into the `target`: a document
+    /// (e.g. a document of an iframe), an element (a custom container)
+    /// or a shadow root. Elements are created by the target document.
+    pub fn new(target : impl Into<Target>) -> Result<Self> {
+        let target = target.into();
+        Ok(Context {
             content : Arc::new(Mutex::new(ContentMap::new())),
             lookup_handler : LookupHandler::new(),
             loaded : AtomicUsize::new(0),
-        }
+            timeout : Mutex::new(None),
+            strict : AtomicBool::new(false),
+            pending : Mutex::new(HashMap::new()),
+            errors : Mutex::new(HashMap::new()),
+            observers : Mutex::new(Vec::new()),
+            state : Mutex::new(HashMap::new()),
+            sheets : Mutex::new(HashMap::new()),
+            links : Mutex::new(HashMap::new()),
+            mapped : Mutex::new(HashSet::new()),
+            document : target.document()?,
+            target,
+        })
     }
-}
 
-impl Context {
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
 
     // pub fn new(content : ContentMap) -> Context {
     //     Context {
@@ -307,16 +847,340 @@ impl Context {
         self.content.lock().unwrap().get(id).cloned()
     }
 
+    /// Access the load state of content `id`
+    fn state<R>(&self, id : &Id, f : impl FnOnce(&mut State) -> R) -> R {
+        f(self.state.lock().unwrap().entry(*id).or_default())
+    }
+
+    /// Returns `true` if content `id` has been loaded by this context
+    pub fn is_loaded(&self, id : &Id) -> bool {
+        self.state(id, |state| state.loaded)
+    }
+
+    /// Returns the blob URL of content `id` within this context
+    /// or the remote `src` URL of the content
+    pub fn url(&self, id : &Id) -> Option<String> {
+        self.get(id).and_then(|content| content.url(self))
+    }
+
+    /// Size of the source of content `id` in bytes. Fetched and remote content
+    /// reports its size once retrieved (`0` before), while compressed
+    /// content reports the size of the compressed data until decompressed.
+    pub fn size(&self, id : &Id) -> usize {
+        self.get(id).map(|content| content.size(self)).unwrap_or_default()
+    }
+
+    /// Set the default timeout applied to each content load
+    /// performed by this context (`None` disables the timeout)
+    pub fn set_timeout(&self, timeout : Option<Duration>) {
+        *self.timeout.lock().unwrap() = timeout;
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        *self.timeout.lock().unwrap()
+    }
+
+    /// Enable or disable strict mode. In strict mode a failure to load
+    /// any dependency aborts the injection of the dependent content with
+    /// [`Error::DependencyFailed`]. Otherwise dependency failures are
+    /// logged and the dependent content is injected regardless.
+    pub fn set_strict(&self, strict : bool) {
+        self.strict.store(strict, Ordering::SeqCst);
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict.load(Ordering::SeqCst)
+    }
+
+    /// Subscribe to load progress events. The returned receiver
+    /// is a [`Stream`](futures::Stream) of [`LoadEvent`] values.
+    pub fn subscribe(&self) -> Receiver<LoadEvent> {
+        let (sender, receiver) = unbounded();
+        self.observers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    fn notify(&self, event : LoadEvent) {
+        self.observers.lock().unwrap().retain(|sender| sender.try_send(event.clone()).is_ok());
+    }
+
+    /// Returns the error produced by the last failed load of content `id`
+    pub fn error(&self, id : &Id) -> Option<Error> {
+        self.errors.lock().unwrap().get(id).cloned()
+    }
+
+    /// Cancel a pending load of content `id`, removing its element
+    /// from DOM. The failed load revokes the blob URL of the content
+    /// unless it is listed in the import map. Returns `false` if the
+    /// content is not currently loading.
+    pub fn cancel(&self, id : &Id) -> Result<bool> {
+        let cancel = self.pending.lock().unwrap().get(id).cloned();
+        match cancel {
+            Some(cancel) => {
+                cancel.cancel()?;
+                Ok(true)
+            },
+            None => Ok(false)
+        }
+    }
+
+    /// Returns the number of live blob URLs held by the content of this context
+    pub fn live_urls(&self) -> usize {
+        self.state.lock().unwrap().values().filter(|state| state.url.is_some()).count()
+    }
+
+    /// Revoke the blob URL of module `id`. Fails if the module
+    /// is still referenced by content that has not been loaded yet.
+    pub fn revoke(&self, id : &Id) -> Result<bool> {
+        let content = self.get(id).ok_or(format!("unable to lookup module `{}`",id))?;
+        let dependents = self.content.lock().unwrap()
+            .values()
+            .filter(|dependent| !self.is_loaded(&dependent.id) && dependent.references(id))
+            .map(|dependent| dependent.ident)
+            .collect::<Vec<_>>();
+        if !dependents.is_empty() {
+            return Err(format!("unable to revoke `{}`: referenced by {}", content.ident, dependents.join(", ")).into());
+        }
+        if self.is_mapped(id) {
+            return Err(format!("unable to revoke `{}`: referenced by the import map", content.ident).into());
+        }
+        content.revoke(self)
+    }
+
+    /// Revoke blob URLs of all loaded content that is no longer
+    /// referenced by content pending load. Returns the number of revoked URLs.
+    pub fn revoke_unreferenced(&self) -> Result<usize> {
+        let ids = self.state.lock().unwrap()
+            .iter()
+            .filter(|(_, state)| state.loaded && state.url.is_some())
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        let mut revoked = 0;
+        for id in ids {
+            if matches!(self.revoke(&id), Ok(true)) {
+                revoked += 1;
+            }
+        }
+        Ok(revoked)
+    }
+
+    /// Unload content `id`, removing its element from DOM (and its shared
+    /// stylesheet or `<link>` elements from attached targets), revoking its blob URL and
+    /// resetting its loaded state. A pending load is cancelled.
+    /// Returns `false` if the content was not loaded.
+    pub fn unload(&self, id : &Id) -> Result<bool> {
+        let content = self.get(id).ok_or(format!("unable to lookup module `{}`",id))?;
+        self.cancel(id)?;
+        let loaded = self.state(id, |state| std::mem::take(&mut state.loaded));
+        if let Some(element) = self.target.get_element_by_id(content.ident) {
+            element.remove();
+        }
+        let adopted = self.sheets.lock().unwrap().remove(id);
+        if let Some((sheet, targets)) = adopted {
+            for target in targets.iter() {
+                sheet.unadopt(target)?;
+            }
+        }
+        let linked = self.links.lock().unwrap().remove(id);
+        for target in linked.into_iter().flatten() {
+            if let Some(element) = target.get_element_by_id(content.ident) {
+                element.remove();
+            }
+        }
+        if !self.is_mapped(id) {
+            content.revoke(self)?;
+        }
+        Ok(loaded)
+    }
+
+    /// Replace content `id` with `content` and load it. A stylesheet is
+    /// swapped once the replacement has loaded (the previous stylesheet is
+    /// retained if the replacement fails to load), while scripts and modules
+    /// are unloaded and re-evaluated. Modules that have already imported
+    /// the previous module keep referencing its original instance.
+    /// `<link>` elements of attached targets are replaced once the
+    /// replacement has loaded.
+    pub async fn replace(self : &Arc<Self>, id : &Id, content : Arc<Content>) -> Result<ContentStatus> {
+        if content.id != *id {
+            return Err(format!("unable to replace `{}`: content id mismatch {} != {}", content.ident, content.id, id).into());
+        }
+        let previous = self.get(id).ok_or(format!("unable to lookup module `{}`",id))?;
+        self.validate_replacement(&content)?;
+        // the retrieved body belongs to the previous content
+        self.state(id, |state| {
+            state.body = None;
+            state.size = 0;
+        });
+
+        // `<link>` elements are set aside so that unloading the previous content retains them
+        let linked = self.links.lock().unwrap().remove(id);
+        let status = self.replace_impl(id, &previous, content.clone()).await;
+        if let Some(targets) = linked {
+            if status.is_err() {
+                self.links.lock().unwrap().insert(*id, targets);
+            } else {
+                let mut result = Ok(());
+                for target in targets {
+                    let element = target.get_element_by_id(previous.ident);
+                    if content.content_type == ContentType::Style {
+                        if let Err(err) = self.link(&content, &target).await {
+                            // the previous element is retained if the replacement fails to load
+                            self.links.lock().unwrap().entry(*id).or_default().push(target);
+                            result = Err(err);
+                            continue;
+                        }
+                    }
+                    if let Some(element) = element {
+                        element.remove();
+                    }
+                }
+                result?;
+            }
+        }
+        status
+    }
+
+    async fn replace_impl(self : &Arc<Self>, id : &Id, previous : &Arc<Content>, content : Arc<Content>) -> Result<ContentStatus> {
+        // stylesheets adopted by attached targets are updated in place
+        let sheet = self.sheets.lock().unwrap().get(id).map(|(sheet,_)| sheet.clone());
+        if let Some(sheet) = sheet.as_ref().filter(|_| !content.is_remote()) {
+            content.clone().load_deps(self.clone()).await?;
+            sheet.replace_sync(&content.text(self).await?)?;
+            if !self.is_loaded(id) {
+                self.content.lock().unwrap().insert(*id, content);
+                return Ok(ContentStatus::Loaded);
+            }
+        }
+
+        if previous.content_type == ContentType::Style && self.is_loaded(id) {
+            let element = self.target.get_element_by_id(previous.ident);
+            // the state of the previous stylesheet is set aside while the replacement loads
+            let state = self.state.lock().unwrap().remove(id).unwrap_or_default();
+            self.content.lock().unwrap().insert(*id, content.clone());
+            let status = match self.load_content(content).await {
+                Ok(status) => status,
+                Err(err) => {
+                    self.content.lock().unwrap().insert(*id, previous.clone());
+                    self.state.lock().unwrap().insert(*id, state);
+                    return Err(err);
+                }
+            };
+            if let Some(element) = element {
+                element.remove();
+            }
+            revoke_urls(state.url, state.map_url)?;
+            Ok(status)
+        } else {
+            self.unload(id)?;
+            self.content.lock().unwrap().insert(*id, content.clone());
+            self.load_content(content).await
+        }
+    }
+
+    /// Instantiate declared stylesheets `ids` within the `target`, typically
+    /// a [`ShadowRoot`](web_sys::ShadowRoot) of a web component. Where supported,
+    /// each stylesheet is constructed once and adopted by every target (see
+    /// [`StyleSheet`]). Otherwise each stylesheet is injected as a `<link>`
+    /// element once per target; blob URLs of such stylesheets remain live
+    /// so that they can be shared across targets.
+    pub async fn attach(self : &Arc<Self>, target : impl Into<Target>, ids : &[Id]) -> Result<()> {
+        self.validate_ids(ids)?;
+        let target = target.into();
+        let futures = ids
+            .iter()
+            .map(|id| {
+                let target = target.clone();
+                async move {
+                    let content = self.get(id).ok_or(Error::NotFound(*id))?;
+                    if content.content_type != ContentType::Style {
+                        return Err(Error::String(format!("unable to attach `{}`: content is not a stylesheet", content.ident)));
+                    }
+                    // content referenced by `@import` rules and `url()` functions
+                    // requires a URL before the stylesheet text is built
+                    content.clone().load_deps(self.clone()).await?;
+                    // constructable stylesheets do not support `@import` rules
+                    if StyleSheet::is_supported() && !content.is_remote() && !content.has_imports() {
+                        let css = content.text(self).await?;
+                        return self.adopt(&content, &css, &target);
+                    }
+                    if target.get_element_by_id(content.ident).is_some() {
+                        return Ok(ContentStatus::Exists);
+                    }
+                    self.link(&content, &target).await
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for result in join_all(futures).await {
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Inject the stylesheet `content` into the `target` as a `<link>` element,
+    /// recording the target so that the element can be unloaded or replaced
+    async fn link(self : &Arc<Self>, content : &Arc<Content>, target : &Target) -> Result<ContentStatus> {
+        let timeout = self.timeout();
+        let cancel = Cancel::new();
+        let start = Instant::now();
+        let (text, url) = cancel.until(content.ident, content.prepare(self), timeout).await?;
+        // the URL is shared across targets, so only the element of a failed load is removed
+        let timeout = timeout.map(|timeout| timeout.saturating_sub(start.elapsed()));
+        let result = content.inject(self, target, &text, url.as_deref(), timeout, &cancel).await;
+        if result.is_err() {
+            cancel.remove_element();
+        } else {
+            self.links.lock().unwrap().entry(content.id).or_default().push(target.clone());
+        }
+        result
+    }
+
+    /// Adopt the shared constructable stylesheet of `content` into the `target`
+    fn adopt(&self, content : &Content, css : &str, target : &Target) -> Result<ContentStatus> {
+        let mut sheets = self.sheets.lock().unwrap();
+        let (sheet, targets) = match sheets.entry(content.id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert((StyleSheet::new(css)?, Vec::new()))
+        };
+        if sheet.adopt(target)? {
+            targets.push(target.clone());
+            Ok(ContentStatus::Loaded)
+        } else {
+            Ok(ContentStatus::Exists)
+        }
+    }
+
     pub async fn load_content(self: &Arc<Self>, content : Arc<Content>) -> Result<ContentStatus> {
+        self.load_content_with_timeout(content, self.timeout()).await
+    }
 
-        if content.is_loaded() {
+    pub async fn load_content_with_timeout(self: &Arc<Self>, content : Arc<Content>, timeout : Option<Duration>) -> Result<ContentStatus> {
+
+        if self.is_loaded(&content.id) {
             Ok(ContentStatus::Exists)
         } else {
-        
+
+            self.validate_ids(&[content.id])?;
             match self.lookup_handler.queue(&content.id).await {
                 RequestType::New(receiver) => {
                     self.loaded.fetch_add(1,Ordering::SeqCst);
-                    let result = content.load_impl(self).await;
+                    let (id, ident) = (content.id, content.ident);
+                    let start = Instant::now();
+                    self.notify(LoadEvent::Started { id, ident, size : content.size(self) });
+                    let result = content.load_impl(self, timeout).await;
+                    let elapsed = start.elapsed();
+                    // the size of fetched and remote content is known once loaded
+                    let size = content.size(self);
+                    match &result {
+                        Ok(_) => {
+                            self.errors.lock().unwrap().remove(&id);
+                            self.notify(LoadEvent::Loaded { id, ident, size, elapsed });
+                        },
+                        Err(err) => {
+                            self.errors.lock().unwrap().insert(id, err.clone());
+                            self.notify(LoadEvent::Failed { id, ident, size, elapsed, error : err.clone() });
+                        }
+                    }
                     self.lookup_handler.complete(&content.id, result).await;
                     receiver.recv().await?
                 },
@@ -327,51 +1191,292 @@ impl Context {
         }
     }
 
-    pub async fn load_ids(self : &Arc<Self>, list : &[Id]) -> Result<()> {
+    /// Validate the entire declared content graph, checking for
+    /// self-references, references to undeclared ids and cycles.
+    pub fn validate(&self) -> Result<()> {
+        let ids = self.content.lock().unwrap().keys().cloned().collect::<Vec<_>>();
+        self.validate_ids(&ids)
+    }
 
-        let start = Instant::now();
+    /// Validate the content graph reachable from `list`
+    pub fn validate_ids(&self, list : &[Id]) -> Result<()> {
+        let map = self.content.lock().unwrap();
+        let mut path = Vec::new();
+        let mut visited = HashSet::new();
+        for id in list {
+            if map.contains_key(id) {
+                validate_graph(&map, id, &mut path, &mut visited)?;
+            }
+        }
+        Ok(())
+    }
 
-        // let mut futures = Vec::with_capacity(list.len());
-        // for id in list {
-        //     if let Some(module) = self.get(id) {
-        //         futures.push(module.load(self));
-        //     }            
-        // }
-        let futures = list
-            .iter()
-            .map(|id| {
-                if let Some(module) = self.get(id) {
-                    Some(module.load(self))
-                } else {
-                    log_error!("Unable to locate module {}", id);
-                    // TODO: panic
-                    None
-                }
-            })
-            .flatten()
-            .collect::<Vec<_>>();
-        
-        for future in futures {
-            match future.await {
-                Ok(_event) => {
+    /// Validate the content graph reachable from the replacement `content`
+    /// and from the content referencing it, as if `content` was declared
+    fn validate_replacement(&self, content : &Arc<Content>) -> Result<()> {
+        let mut map = self.content.lock().unwrap().clone();
+        map.insert(content.id, content.clone());
+        let mut path = Vec::new();
+        let mut visited = HashSet::new();
+        validate_graph(&map, &content.id, &mut path, &mut visited)?;
+        let dependents = map.values().filter(|dependent| dependent.references(&content.id)).map(|dependent| dependent.id).collect::<Vec<_>>();
+        for id in dependents {
+            validate_graph(&map, &id, &mut path, &mut visited)?;
+        }
+        Ok(())
+    }
+
+    /// Load content `list` along with its dependencies. Fails only if the
+    /// content graph is invalid; load failures are reported in the returned
+    /// [`LoadReport`].
+    pub async fn load_ids(self : &Arc<Self>, list : &[Id]) -> Result<LoadReport> {
+
+        self.validate_ids(list)?;
+
+        let start = Instant::now();
 
+        let mut status = Vec::with_capacity(list.len());
+        for id in list {
+            let result = match self.get(id) {
+                Some(module) => module.load(self).await,
+                None => Err(Error::NotFound(*id)),
+            };
+            match result {
+                Ok(result) => {
+                    status.push((*id, result));
                 },
                 Err(err) => {
                     log_error!("{}", err);
+                    status.push((*id, ContentStatus::Error(err)));
                 }
             }
         }
 
+        let failed = {
+            let errors = self.errors.lock().unwrap();
+            self.dependencies(list)
+                .into_iter()
+                .filter_map(|id| errors.get(&id).map(|err| (id, err.clone())))
+                .collect::<Vec<_>>()
+        };
+
         let elapsed = start.elapsed();
         let loaded = self.loaded.load(Ordering::SeqCst);
         log_info!("Loaded {} references in {} msec", loaded, elapsed.as_millis());
 
+        Ok(LoadReport { status, failed, elapsed })
+    }
+
+    /// Create blob URLs of declared modules `ids` (along with the modules they
+    /// import) and inject an [`ImportMap`] mapping their idents to these URLs.
+    /// Module sources can then import each other by ident using bare specifiers
+    /// (such as `"lit"` or `"app/utils"`) and are injected unchanged, without
+    /// waiting for dependencies to load before their text is built. The import
+    /// map must be injected before any module is loaded; mapped URLs remain
+    /// live for the lifetime of the context.
+    pub async fn import_map(&self, ids : &[Id]) -> Result<ImportMap> {
+        if csp::is_inline() {
+            return Err("import maps are not supported with inline content".into());
+        }
+        self.validate_ids(ids)?;
+
+        let mut order = Vec::new();
+        for id in ids {
+            self.import_order(id, &mut order)?;
+        }
+
+        let mut map = ImportMap::new();
+        for content in order {
+            let url = match content.url(self) {
+                Some(url) => url,
+                None => content.create_blob_url(self, &content.text(self).await?)?,
+            };
+            map.insert(content.ident, &url);
+            self.mapped.lock().unwrap().insert(content.id);
+        }
+        map.inject(&self.target)?;
+        Ok(map)
+    }
+
+    /// Collect modules imported by module `id` followed by the module itself
+    fn import_order(&self, id : &Id, order : &mut Vec<Arc<Content>>) -> Result<()> {
+        if order.iter().any(|content| content.id == *id) {
+            return Ok(());
+        }
+        let content = self.get(id).ok_or(Error::NotFound(*id))?;
+        if content.content_type != ContentType::Module {
+            return Err(Error::String(format!("unable to map `{}`: content is not a module", content.ident)));
+        }
+        if let Some(references) = content.references {
+            for (kind,_,reference) in references.iter() {
+                if kind.content_type() == Some(ContentType::Module) && reference != id {
+                    self.import_order(reference, order)?;
+                }
+            }
+        }
+        order.push(content);
         Ok(())
     }
+
+    /// Load module `id` (along with its dependencies) and import it using
+    /// a dynamic `import()` of its blob URL, returning the module namespace.
+    /// The namespace provides access to the module exports from Rust without
+    /// exposing them as globals. Importing an already loaded module returns
+    /// the instance evaluated when it was loaded.
+    pub async fn import(self : &Arc<Self>, id : &Id) -> Result<ModuleNamespace> {
+        let content = self.get(id).ok_or(Error::NotFound(*id))?;
+        if content.content_type != ContentType::Module {
+            return Err(Error::String(format!("unable to import `{}`: content is not a module", content.ident)));
+        }
+        self.validate_ids(&[*id])?;
+        self.load_content(content.clone()).await?;
+        let url = content.url(self).ok_or_else(|| Error::String(format!("unable to import `{}`: module has no live URL (revoked or injected inline)", content.ident)))?;
+        let ident = content.ident;
+        local(async move {
+            Ok(import::import(&url).await?.with_ident(ident))
+        }).await
+    }
+
+    /// Start a worker running the declared script or module `id`. The blob URLs of
+    /// a worker module and of the modules it imports are created without injecting
+    /// them into the page. The optional `name` identifies a shared worker.
+    pub async fn worker(self : &Arc<Self>, id : &Id, kind : WorkerKind, name : Option<&str>) -> Result<Worker> {
+        let content = self.get(id).ok_or(Error::NotFound(*id))?;
+        let expected = if kind.is_module() { ContentType::Module } else { ContentType::Script };
+        if content.content_type != expected {
+            return Err(Error::String(format!("unable to start {:?} worker `{}`: content is declared as {:?}", kind, content.ident, content.content_type)));
+        }
+        self.validate_ids(&[*id])?;
+        let url = content.worker_url(self.clone()).await?;
+        Worker::with_url(&url, kind, name)
+    }
+
+    /// Load the binary asset `id` and return its blob URL, which
+    /// can be used by UI code (e.g. as the `src` of an image)
+    pub async fn asset_url(self : &Arc<Self>, id : &Id) -> Result<String> {
+        let content = self.get(id).ok_or(Error::NotFound(*id))?;
+        if content.content_type != ContentType::Asset {
+            return Err(Error::String(format!("`{}` is not an asset", content.ident)));
+        }
+        self.load_content(content.clone()).await?;
+        content.url(self).ok_or_else(|| Error::String(format!("asset `{}` has no URL", content.ident)))
+    }
+
+    /// Load the font asset `id` and register it as the font `family`
+    /// with the document of this context (see [`asset::load_font`])
+    pub async fn load_font(self : &Arc<Self>, family : &str, id : &Id) -> Result<()> {
+        let url = self.asset_url(id).await?;
+        let document = self.document.clone();
+        let family = family.to_string();
+        local(async move {
+            asset::load_font(&document, &family, &url, None).await?;
+            Ok(())
+        }).await
+    }
+
+    /// Returns `true` if content `id` is listed in the import map
+    pub fn is_mapped(&self, id : &Id) -> bool {
+        self.mapped.lock().unwrap().contains(id)
+    }
+
+    /// Returns ids of the content in `list` and all of its
+    /// (direct and indirect) dependencies
+    pub fn dependencies(&self, list : &[Id]) -> Vec<Id> {
+        let map = self.content.lock().unwrap();
+        let mut ids = Vec::new();
+        let mut pending = list.to_vec();
+        while let Some(id) = pending.pop() {
+            if ids.contains(&id) {
+                continue;
+            }
+            if let Some(content) = map.get(&id) {
+                if let Some(references) = content.references {
+                    pending.extend(references.iter().map(|(_,_,id)| *id));
+                }
+                ids.push(id);
+            }
+        }
+        ids
+    }
+}
+
+/// Depth-first traversal of the content graph starting at `id`.
+/// `path` holds the ids currently being visited (used to detect cycles)
+/// and `visited` the ids whose subgraphs have been validated.
+fn validate_graph(map : &ContentMap, id : &Id, path : &mut Vec<Id>, visited : &mut HashSet<Id>) -> Result<()> {
+    if visited.contains(id) {
+        return Ok(());
+    }
+
+    if let Some(pos) = path.iter().position(|item| item == id) {
+        let cycle = path[pos..]
+            .iter()
+            .chain(std::iter::once(id))
+            .map(|id| map.get(id).map(|content| content.ident.to_string()).unwrap_or_else(|| id.to_string()))
+            .collect();
+        return Err(Error::Cycle(cycle));
+    }
+
+    let content = map.get(id).expect("validate_graph(): missing content");
+    if let Some(references) = content.references {
+        path.push(*id);
+        for (kind,_,reference) in references.iter() {
+            if reference == id {
+                return Err(Error::SelfReference(content.ident.to_string()));
+            }
+            let target = map.get(reference).ok_or_else(|| Error::DanglingReference(content.ident.to_string(), *reference))?;
+            if let Some(expected) = kind.content_type() {
+                if target.content_type != expected {
+                    let msg = format!("referenced as {:?} but declared as {:?}", expected, target.content_type);
+                    return Err(Error::ReferenceType(content.ident.to_string(), target.ident.to_string(), msg));
+                }
+            }
+            if kind.content_type() == Some(ContentType::Module) && content.content_type != ContentType::Module {
+                let msg = format!("imports are not supported by {:?} content", content.content_type);
+                return Err(Error::ReferenceType(content.ident.to_string(), target.ident.to_string(), msg));
+            }
+            if kind.is_css() && content.content_type != ContentType::Style {
+                let msg = format!("CSS references are not supported by {:?} content", content.content_type);
+                return Err(Error::ReferenceType(content.ident.to_string(), target.ident.to_string(), msg));
+            }
+            if kind.content_type() == Some(ContentType::Module) && content.is_remote() {
+                let msg = "imports are not supported by remote content".to_string();
+                return Err(Error::ReferenceType(content.ident.to_string(), target.ident.to_string(), msg));
+            }
+            validate_graph(map, reference, path, visited)?;
+        }
+        path.pop();
+    }
+
+    visited.insert(*id);
+    Ok(())
+}
+
+/// Revoke the blob `url` of content along with the blob URL of its source map.
+/// Returns `false` if there is no `url` to revoke.
+fn revoke_urls(url : Option<String>, map_url : Option<String>) -> Result<bool> {
+    if let Some(map_url) = map_url {
+        revoke_blob_url(&map_url)?;
+    }
+    match url {
+        Some(url) => revoke_blob_url(&url),
+        None => Ok(false)
+    }
+}
+
+/// Matches CSS `@import` rules (`import` or `quoted`) and `url()` functions (`url`)
+fn css_reference() -> &'static Regex {
+    static REGEX : OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r#"@import\s+(?:url\(\s*["']?(?P<import>[^"')\s]+)["']?\s*\)|["'](?P<quoted>[^"']+)["'])|url\(\s*["']?(?P<url>[^"')\s]+)["']?\s*\)"#).unwrap()
+    })
 }
 
 static mut CONTEXT: Option<Arc<Context>> = None;
 
+/// Returns the global context injecting into the `head` (or `body`)
+/// of the current document. Use [`Context::new`] to create contexts
+/// bound to other documents or root elements.
 pub fn context() -> Arc<Context> {
     unsafe {
         if let Some(context) = CONTEXT.as_ref() {
@@ -384,8 +1489,70 @@ pub fn context() -> Arc<Context> {
     }
 }
 
+/// Declare content within the global [`context()`]
 pub fn declare(content : ContentList) -> Arc<Context> {
     let ctx = context();
     ctx.declare(content);
     ctx
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(id : Id, ident : &'static str, content_type : ContentType, references : &'static [(Reference, Option<&'static str>, Id)]) -> (Id, Arc<Content>) {
+        let references = if references.is_empty() { None } else { Some(references) };
+        (id, Arc::new(Content { content_type, id, ident, references, ..Default::default() }))
+    }
+
+    fn validate(map : &ContentMap, id : Id) -> Result<()> {
+        validate_graph(map, &id, &mut Vec::new(), &mut HashSet::new())
+    }
+
+    #[test]
+    fn validate_graph_accepts_valid_graph() {
+        let map = ContentMap::from([
+            content(1, "app", ContentType::Module, &[(Reference::Module, Some("{ a }"), 2), (Reference::Style, None, 3)]),
+            content(2, "lib", ContentType::Module, &[]),
+            content(3, "theme", ContentType::Style, &[(Reference::Url, Some("font.woff2"), 4)]),
+            content(4, "font", ContentType::Asset, &[]),
+        ]);
+        assert!(validate(&map, 1).is_ok());
+    }
+
+    #[test]
+    fn validate_graph_detects_cycles() {
+        let map = ContentMap::from([
+            content(1, "a", ContentType::Module, &[(Reference::Module, None, 2)]),
+            content(2, "b", ContentType::Module, &[(Reference::Module, None, 3)]),
+            content(3, "c", ContentType::Module, &[(Reference::Module, None, 2)]),
+        ]);
+        match validate(&map, 1) {
+            Err(Error::Cycle(cycle)) => assert_eq!(cycle, vec!["b", "c", "b"]),
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn validate_graph_detects_self_references() {
+        let map = ContentMap::from([content(1, "a", ContentType::Module, &[(Reference::Module, None, 1)])]);
+        assert!(matches!(validate(&map, 1), Err(Error::SelfReference(ident)) if ident == "a"));
+    }
+
+    #[test]
+    fn validate_graph_detects_dangling_references() {
+        let map = ContentMap::from([content(1, "a", ContentType::Module, &[(Reference::Export, Some("*"), 2)])]);
+        assert!(matches!(validate(&map, 1), Err(Error::DanglingReference(ident, 2)) if ident == "a"));
+    }
+
+    #[test]
+    fn validate_graph_detects_wrong_reference_types() {
+        let map = ContentMap::from([
+            content(1, "a", ContentType::Module, &[(Reference::Module, None, 2)]),
+            content(2, "b", ContentType::Style, &[]),
+            content(3, "c", ContentType::Script, &[(Reference::Url, Some("b.css"), 2)]),
+        ]);
+        assert!(matches!(validate(&map, 1), Err(Error::ReferenceType(ident, target, _)) if ident == "a" && target == "b"));
+        assert!(matches!(validate(&map, 3), Err(Error::ReferenceType(ident, _, _)) if ident == "c"));
+    }
+}
@@ -0,0 +1,93 @@
+//!
+//! Element attribute options applied to injected `<script>`, `<link>`
+//! and `<style>` elements.
+//!
+//! Example:
+//!
+//! ```rust
+//! let options = Options::new().with_media("print").with_data("theme", "dark");
+//! inject_blob_with_options(Content::Style(Some("print"), PRINT_CSS), &options).await?;
+//! ```
+//!
+
+use web_sys::Element;
+use crate::result::Result;
+
+/// Attributes set on injected elements
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    attributes : Vec<(String, String)>,
+}
+
+impl Options {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set an arbitrary attribute. Attributes set here are applied after
+    /// the attributes set by the injector, so they can override them
+    /// (e.g. `rel` set to `alternate stylesheet`).
+    pub fn with_attribute(mut self, name : &str, value : &str) -> Self {
+        self.attributes.retain(|(attribute,_)| attribute != name);
+        self.attributes.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set the `media` query of a stylesheet (e.g. `print` or `(prefers-color-scheme: dark)`)
+    pub fn with_media(self, media : &str) -> Self {
+        self.with_attribute("media", media)
+    }
+
+    /// Set the `title` of a stylesheet (used for alternate stylesheets)
+    pub fn with_title(self, title : &str) -> Self {
+        self.with_attribute("title", title)
+    }
+
+    /// Set the `defer` attribute of a classic script
+    pub fn with_defer(self) -> Self {
+        self.with_attribute("defer", "")
+    }
+
+    /// Set the `async` attribute of a script
+    pub fn with_async(self) -> Self {
+        self.with_attribute("async", "")
+    }
+
+    /// Set the `nomodule` attribute of a classic script
+    pub fn with_nomodule(self) -> Self {
+        self.with_attribute("nomodule", "")
+    }
+
+    /// Set the `crossorigin` attribute (`anonymous` or `use-credentials`)
+    pub fn with_crossorigin(self, crossorigin : &str) -> Self {
+        self.with_attribute("crossorigin", crossorigin)
+    }
+
+    /// Set the `referrerpolicy` attribute
+    pub fn with_referrer_policy(self, policy : &str) -> Self {
+        self.with_attribute("referrerpolicy", policy)
+    }
+
+    /// Set a `data-<name>` attribute
+    pub fn with_data(self, name : &str, value : &str) -> Self {
+        self.with_attribute(&format!("data-{}", name), value)
+    }
+
+    /// Returns the value of the attribute `name` if set
+    pub fn get(&self, name : &str) -> Option<&str> {
+        self.attributes.iter().find(|(attribute,_)| attribute == name).map(|(_,value)| value.as_str())
+    }
+
+    pub fn attributes(&self) -> &[(String, String)] {
+        &self.attributes
+    }
+
+    /// Set the attributes on the `element`
+    pub fn apply(&self, element : &Element) -> Result<()> {
+        for (name, value) in self.attributes.iter() {
+            element.set_attribute(name, value)?;
+        }
+        Ok(())
+    }
+}
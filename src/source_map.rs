@@ -0,0 +1,173 @@
+//!
+//! [Source map](https://developer.mozilla.org/en-US/docs/Tools/Debugger/How_to/Use_a_source_map)
+//! handling for injected scripts.
+//!
+//! Injected scripts are loaded from `blob:` URLs, where relative
+//! `//# sourceMappingURL` comments can not be resolved, so by default
+//! these comments are stripped. Absolute (scheme-qualified) map URLs
+//! are kept unless [`Mode::Strip`] is selected. The [`Config`] set via [`set_config`] allows
+//! source maps (supplied alongside the content or embedded in it as a data URL)
+//! to be preserved as inline data URLs or as separate blob URLs. Mappings are
+//! offset to account for the import preamble generated by the loader. A
+//! `//# sourceURL=<ident>` comment can be appended so that injected code
+//! is listed under a meaningful name in DevTools and stack traces.
+//!
+//! Example:
+//!
+//! ```rust
+//! source_map::set_config(Config::default().with_mode(Mode::Inline).with_source_url(true));
+//! ```
+//!
+
+use std::sync::{Mutex, OnceLock};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use js_sys::{Reflect, JSON};
+use regex::Regex;
+use crate::blob::create_blob_url;
+use crate::result::Result;
+
+/// Handling of source maps of injected scripts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Strip `sourceMappingURL` comments
+    #[default]
+    Strip,
+    /// Embed source maps as `data:` URLs
+    Inline,
+    /// Create a separate blob URL for each source map
+    /// (see [`process`] for its lifetime)
+    Blob,
+}
+
+/// Source map settings applied to injected scripts
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub mode : Mode,
+    /// Append `//# sourceURL=<ident>` to injected scripts
+    pub source_url : bool,
+}
+
+impl Config {
+    pub fn with_mode(mut self, mode : Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_source_url(mut self, source_url : bool) -> Self {
+        self.source_url = source_url;
+        self
+    }
+}
+
+static CONFIG : Mutex<Option<Config>> = Mutex::new(None);
+
+/// Set the source map settings applied to all subsequently injected scripts
+pub fn set_config(config : Config) {
+    CONFIG.lock().unwrap().replace(config);
+}
+
+/// Returns the current source map settings
+pub fn config() -> Config {
+    CONFIG.lock().unwrap().clone().unwrap_or_default()
+}
+
+fn source_mapping_url() -> &'static Regex {
+    static REGEX : OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"(?m)^[ \t]*//[#@][ \t]*sourceMappingURL=(\S*)[ \t]*$").unwrap())
+}
+
+fn source_url() -> &'static Regex {
+    static REGEX : OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"(?m)^[ \t]*//[#@][ \t]*sourceURL=\S*[ \t]*$").unwrap())
+}
+
+/// Returns the URL of the last `sourceMappingURL` comment in `text`
+pub fn source_map_url(text : &str) -> Option<&str> {
+    source_mapping_url()
+        .captures_iter(text)
+        .last()
+        .and_then(|captures| captures.get(1))
+        .map(|url| url.as_str())
+}
+
+/// Returns `true` if the map `url` is scheme-qualified and
+/// can be resolved from a `blob:` URL (`data:` URLs excluded)
+pub fn is_absolute(url : &str) -> bool {
+    match url.split_once(':') {
+        Some((scheme, _)) => {
+            scheme != "data"
+                && scheme.starts_with(|c : char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        },
+        None => false,
+    }
+}
+
+/// Remove `sourceMappingURL` comments from `text`
+pub fn strip(text : &str) -> String {
+    source_mapping_url().replace_all(text, "").to_string()
+}
+
+/// Decode a source map embedded as a base64 `data:` URL
+pub fn decode_data_url(url : &str) -> Option<String> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    if !header.ends_with(";base64") {
+        return None;
+    }
+    let bytes = STANDARD.decode(data).ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Offset the `mappings` of the source `map` by `lines`
+/// generated lines prepended to the script
+pub fn offset(map : &str, lines : usize) -> Result<String> {
+    if lines == 0 {
+        return Ok(map.to_string());
+    }
+    let map = JSON::parse(map)?;
+    let mappings = Reflect::get(&map, &"mappings".into())?.as_string().unwrap_or_default();
+    let mappings = ";".repeat(lines) + &mappings;
+    Reflect::set(&map, &"mappings".into(), &mappings.into())?;
+    Ok(JSON::stringify(&map)?.into())
+}
+
+/// Prepare script `text` injected as `ident` according to the current
+/// [`Config`]. The source `map` (or a map embedded in `text` as a `data:` URL)
+/// is offset by `lines` lines prepended to the original script. In [`Mode::Blob`]
+/// the blob URL of the map is created once and stored in `map_url`, so that
+/// repeated calls yield the same text; the caller revokes it along with the script.
+/// Without a supplied `map`, an absolute map URL in `text` is left unchanged
+/// outside of [`Mode::Strip`]; such a map is not offset by `lines`.
+pub fn process(text : &str, ident : &str, map : Option<&str>, lines : usize, map_url : &mut Option<String>) -> Result<String> {
+    let config = config();
+    let remote = config.mode != Mode::Strip && map.is_none() && source_map_url(text).is_some_and(is_absolute);
+    let map = match config.mode {
+        Mode::Strip => None,
+        _ if remote => None,
+        _ => map.map(String::from).or_else(|| source_map_url(text).and_then(decode_data_url)),
+    };
+
+    let mut text = if remote { text.to_string() } else { strip(text) };
+    if config.source_url {
+        text = source_url().replace_all(&text, "").to_string();
+    }
+
+    if let Some(map) = map {
+        let map = offset(&map, lines)?;
+        let url = match config.mode {
+            Mode::Blob => match map_url.as_ref() {
+                Some(url) => url.clone(),
+                None => map_url.insert(create_blob_url(map.as_bytes(), Some("application/json"))?).clone()
+            },
+            _ => format!("data:application/json;base64,{}", STANDARD.encode(map)),
+        };
+        text += &format!("\n//# sourceMappingURL={}", url);
+    }
+
+    if config.source_url {
+        text += &format!("\n//# sourceURL={}", ident);
+    }
+
+    Ok(text)
+}
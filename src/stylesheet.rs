@@ -0,0 +1,146 @@
+//!
+//! Constructable stylesheets ([`CSSStyleSheet`](https://developer.mozilla.org/en-US/docs/Web/API/CSSStyleSheet/CSSStyleSheet))
+//! that are built once and adopted into the document and any number of shadow roots via
+//! [`adoptedStyleSheets`](https://developer.mozilla.org/en-US/docs/Web/API/Document/adoptedStyleSheets).
+//!
+//! Use [`StyleSheet::is_supported`] to detect support; [`adopt_css`] falls back to
+//! injecting a `<link>` element when constructable stylesheets are not available.
+//!
+
+use js_sys::{Array, Function, Object, Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::Node;
+use crate::cancel::Cancel;
+use crate::inject::{inject_blob_into, Content};
+use crate::options::Options;
+use crate::result::Result;
+use crate::target::Target;
+use crate::utils::window;
+
+/// Constructable stylesheet
+#[derive(Clone, Debug)]
+pub struct StyleSheet {
+    sheet : Object,
+}
+
+unsafe impl Send for StyleSheet {}
+unsafe impl Sync for StyleSheet {}
+
+impl StyleSheet {
+
+    /// Returns `true` if the environment supports constructable
+    /// stylesheets and `adoptedStyleSheets`
+    pub fn is_supported() -> bool {
+        let has = |class : &str, property : &str| -> bool {
+            Reflect::get(&window(), &class.into())
+                .and_then(|class| Reflect::get(&class, &"prototype".into()))
+                .and_then(|prototype| Reflect::has(&prototype, &property.into()))
+                .unwrap_or(false)
+        };
+        has("CSSStyleSheet", "replaceSync") && has("Document", "adoptedStyleSheets")
+    }
+
+    /// Create a stylesheet from `css` text (using `replaceSync()`)
+    pub fn new(css : &str) -> Result<Self> {
+        let sheet = Self::construct()?;
+        sheet.replace_sync(css)?;
+        Ok(sheet)
+    }
+
+    /// Create a stylesheet from `css` text using `replace()`, which
+    /// also processes `@import` rules asynchronously
+    pub async fn new_async(css : &str) -> Result<Self> {
+        let sheet = Self::construct()?;
+        sheet.replace(css).await?;
+        Ok(sheet)
+    }
+
+    fn construct() -> Result<Self> {
+        let class = Reflect::get(&window(), &"CSSStyleSheet".into())?;
+        let sheet = Reflect::construct(class.unchecked_ref(), &Array::new())?;
+        Ok(StyleSheet { sheet : sheet.unchecked_into() })
+    }
+
+    /// Underlying `CSSStyleSheet` object
+    pub fn sheet(&self) -> &Object {
+        &self.sheet
+    }
+
+    /// Replace the stylesheet rules with `css`. Changes apply
+    /// to all documents and shadow roots adopting this stylesheet.
+    pub fn replace_sync(&self, css : &str) -> Result<()> {
+        let replace_sync = Reflect::get(&self.sheet, &"replaceSync".into())?;
+        replace_sync.unchecked_ref::<Function>().call1(&self.sheet, &css.into())?;
+        Ok(())
+    }
+
+    /// Asynchronously replace the stylesheet rules with `css`
+    pub async fn replace(&self, css : &str) -> Result<()> {
+        let replace = Reflect::get(&self.sheet, &"replace".into())?;
+        let promise = replace.unchecked_ref::<Function>().call1(&self.sheet, &css.into())?;
+        JsFuture::from(promise.unchecked_into::<Promise>()).await?;
+        Ok(())
+    }
+
+    /// Adopt the stylesheet into the `target`. An element target adopts
+    /// into its root node (its shadow root or its document). Returns `false`
+    /// if the stylesheet has already been adopted.
+    pub fn adopt(&self, target : &Target) -> Result<bool> {
+        let owner = Self::owner(target)?;
+        let sheets = Self::adopted(&owner)?;
+        if sheets.includes(&self.sheet, 0) {
+            return Ok(false);
+        }
+        // older implementations expose a frozen array,
+        // so a new array is always assigned
+        let sheets = sheets.concat(&Array::of1(&self.sheet));
+        Reflect::set(&owner, &"adoptedStyleSheets".into(), &sheets)?;
+        Ok(true)
+    }
+
+    /// Remove the stylesheet from the `target`
+    pub fn unadopt(&self, target : &Target) -> Result<bool> {
+        let owner = Self::owner(target)?;
+        let sheets = Self::adopted(&owner)?;
+        if !sheets.includes(&self.sheet, 0) {
+            return Ok(false);
+        }
+        let sheet : JsValue = self.sheet.clone().into();
+        let sheets = sheets.filter(&mut |item, _, _| item != sheet);
+        Reflect::set(&owner, &"adoptedStyleSheets".into(), &sheets)?;
+        Ok(true)
+    }
+
+    /// Returns `true` if the stylesheet is adopted by the `target`
+    pub fn is_adopted(&self, target : &Target) -> Result<bool> {
+        Ok(Self::adopted(&Self::owner(target)?)?.includes(&self.sheet, 0))
+    }
+
+    /// Document or shadow root owning `adoptedStyleSheets` for the `target`
+    fn owner(target : &Target) -> Result<JsValue> {
+        match target {
+            Target::Document(document) => Ok(document.clone().into()),
+            Target::ShadowRoot(shadow_root) => Ok(shadow_root.clone().into()),
+            Target::Element(element) => Ok(element.unchecked_ref::<Node>().get_root_node().into()),
+        }
+    }
+
+    fn adopted(owner : &JsValue) -> Result<Array> {
+        Ok(Array::from(&Reflect::get(owner, &"adoptedStyleSheets".into())?))
+    }
+}
+
+/// Apply `css` to the `target` using a constructable stylesheet, falling back
+/// to a `<link>` element (identified by `id`) if constructable stylesheets are
+/// not supported. Returns the created [`StyleSheet`] if adopted.
+pub async fn adopt_css(target : &Target, id : Option<&str>, css : &str) -> Result<Option<StyleSheet>> {
+    if StyleSheet::is_supported() {
+        let sheet = StyleSheet::new(css)?;
+        sheet.adopt(target)?;
+        Ok(Some(sheet))
+    } else {
+        inject_blob_into(target, Content::Style(id, css.as_bytes()), &Options::default(), None, &Cancel::new()).await?;
+        Ok(None)
+    }
+}
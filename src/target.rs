@@ -0,0 +1,98 @@
+//!
+//! Injection targets: a [`Document`], an arbitrary [`Element`]
+//! or a [`ShadowRoot`] into which content elements are appended.
+//!
+
+use web_sys::{Document, Element, Node, ShadowRoot, Window};
+use crate::result::Result;
+use crate::utils::{document, head_or_body};
+
+/// Injection target
+#[derive(Clone)]
+pub enum Target {
+    /// Injects into the `head` (or `body`) element of the document
+    Document(Document),
+    /// Injects into the element (a custom container)
+    Element(Element),
+    /// Injects into the shadow tree
+    ShadowRoot(ShadowRoot),
+}
+
+unsafe impl Send for Target {}
+unsafe impl Sync for Target {}
+
+impl Default for Target {
+    fn default() -> Self {
+        Target::Document(document())
+    }
+}
+
+impl From<Document> for Target {
+    fn from(document: Document) -> Self {
+        Target::Document(document)
+    }
+}
+
+impl From<Element> for Target {
+    fn from(element: Element) -> Self {
+        Target::Element(element)
+    }
+}
+
+impl From<ShadowRoot> for Target {
+    fn from(shadow_root: ShadowRoot) -> Self {
+        Target::ShadowRoot(shadow_root)
+    }
+}
+
+impl Target {
+
+    /// Document used to create elements for this target
+    pub fn document(&self) -> Result<Document> {
+        match self {
+            Target::Document(document) => Ok(document.clone()),
+            Target::Element(element) => Ok(element.owner_document().ok_or("Unable to get owner document of the target element")?),
+            Target::ShadowRoot(shadow_root) => Ok(shadow_root.owner_document().ok_or("Unable to get owner document of the shadow root")?),
+        }
+    }
+
+    /// Window of the target document (e.g. the window of an iframe)
+    pub fn window(&self) -> Result<Window> {
+        Ok(self.document()?.default_view().ok_or("Unable to get window of the target document")?)
+    }
+
+    /// Node to which the injected elements are appended
+    pub fn container(&self) -> Result<Node> {
+        match self {
+            Target::Document(document) => Ok(head_or_body(document)?.into()),
+            Target::Element(element) => Ok(element.clone().into()),
+            Target::ShadowRoot(shadow_root) => Ok(shadow_root.clone().into()),
+        }
+    }
+
+    /// Append `node` to the target container
+    pub fn append(&self, node : &Node) -> Result<()> {
+        self.container()?.append_child(node)?;
+        Ok(())
+    }
+
+    /// Locate an element with the `id` attribute within the target
+    pub fn get_element_by_id(&self, id : &str) -> Option<Element> {
+        match self {
+            Target::Document(document) => document.get_element_by_id(id),
+            // ids are compared directly, as they may contain
+            // characters that are not valid in a selector
+            Target::Element(element) => {
+                let elements = element.get_elements_by_tag_name("*");
+                (0..elements.length())
+                    .filter_map(|index| elements.item(index))
+                    .find(|element| element.id() == id)
+            },
+            Target::ShadowRoot(shadow_root) => shadow_root.get_element_by_id(id),
+        }
+    }
+
+    pub fn is_shadow_root(&self) -> bool {
+        matches!(self, Target::ShadowRoot(_))
+    }
+}
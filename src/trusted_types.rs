@@ -0,0 +1,164 @@
+//!
+//! [Trusted Types](https://developer.mozilla.org/en-US/docs/Web/API/Trusted_Types_API)
+//! integration for DOM sinks used by this crate.
+//!
+//! When a policy is installed via [`TrustedTypesPolicy::install`], script URLs,
+//! inline script text and HTML assigned by this crate are passed through the
+//! named policy (`createScriptURL`, `createScript` and `createHTML`), allowing
+//! applications to enable `require-trusted-types-for 'script'`. Without an
+//! installed policy (or in environments without Trusted Types support) plain
+//! strings are assigned.
+//!
+//! Example:
+//!
+//! ```rust
+//! TrustedTypesPolicy::new("workflow-dom")
+//!     .with_script_url_filter(|url| url.starts_with("blob:"))
+//!     .install()?;
+//! ```
+//!
+
+use std::sync::{Arc, Mutex};
+use js_sys::{Function, Object, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::Element;
+use crate::result::Result;
+use crate::utils::window;
+
+/// Filter deciding if a value may be converted to a trusted type
+pub type Filter = Arc<dyn Fn(&str) -> bool>;
+
+struct Installed {
+    policy : Object,
+}
+
+unsafe impl Send for Installed {}
+
+static POLICY : Mutex<Option<Installed>> = Mutex::new(None);
+
+/// Trusted Types policy configuration
+pub struct TrustedTypesPolicy {
+    name : String,
+    script_url : Option<Filter>,
+    script : Option<Filter>,
+    html : Option<Filter>,
+}
+
+impl TrustedTypesPolicy {
+
+    /// Create a policy configuration for a policy called `name`.
+    /// The name must be allowed by the `trusted-types` CSP directive.
+    pub fn new(name : &str) -> Self {
+        TrustedTypesPolicy {
+            name : name.to_string(),
+            script_url : None,
+            script : None,
+            html : None,
+        }
+    }
+
+    /// Restrict script URLs accepted by the policy
+    pub fn with_script_url_filter(mut self, filter : impl Fn(&str) -> bool + 'static) -> Self {
+        self.script_url = Some(Arc::new(filter));
+        self
+    }
+
+    /// Restrict inline script text accepted by the policy
+    pub fn with_script_filter(mut self, filter : impl Fn(&str) -> bool + 'static) -> Self {
+        self.script = Some(Arc::new(filter));
+        self
+    }
+
+    /// Restrict HTML accepted by the policy
+    pub fn with_html_filter(mut self, filter : impl Fn(&str) -> bool + 'static) -> Self {
+        self.html = Some(Arc::new(filter));
+        self
+    }
+
+    /// Returns `true` if the environment supports Trusted Types
+    pub fn is_supported() -> bool {
+        trusted_types().is_some()
+    }
+
+    /// Create the policy and use it for all subsequent DOM sink
+    /// assignments. Returns `false` if Trusted Types are not supported.
+    pub fn install(self) -> Result<bool> {
+        let factory = match trusted_types() {
+            Some(factory) => factory,
+            None => return Ok(false),
+        };
+
+        let rules = Object::new();
+        for (method, filter) in [("createScriptURL", self.script_url), ("createScript", self.script), ("createHTML", self.html)] {
+            let closure = Closure::wrap(Box::new(move |value : String| -> std::result::Result<JsValue, JsValue> {
+                match &filter {
+                    Some(filter) if !filter(&value) => Err(JsValue::from(js_sys::Error::new(&format!("trusted types: value rejected by {}", method)))),
+                    _ => Ok(JsValue::from(value)),
+                }
+            }) as Box<dyn Fn(String) -> std::result::Result<JsValue, JsValue>>);
+            Reflect::set(&rules, &method.into(), closure.as_ref())?;
+            // the policy retains the rules for the lifetime of the page
+            closure.forget();
+        }
+
+        let create_policy = Reflect::get(&factory, &"createPolicy".into())?;
+        let policy = create_policy
+            .unchecked_ref::<Function>()
+            .call2(&factory, &self.name.into(), &rules)?;
+        POLICY.lock().unwrap().replace(Installed { policy : policy.unchecked_into() });
+        Ok(true)
+    }
+}
+
+fn trusted_types() -> Option<JsValue> {
+    Reflect::get(&window(), &"trustedTypes".into())
+        .ok()
+        .filter(|factory| !factory.is_undefined() && !factory.is_null())
+}
+
+/// Pass `value` through the `method` of the installed policy
+/// or return it as a string if no policy is installed
+fn create(method : &str, value : &str) -> Result<JsValue> {
+    let policy = POLICY.lock().unwrap().as_ref().map(|installed| installed.policy.clone());
+    match policy {
+        Some(policy) => {
+            let create = Reflect::get(&policy, &method.into())?;
+            Ok(create.unchecked_ref::<Function>().call1(&policy, &value.into())?)
+        },
+        None => Ok(value.into())
+    }
+}
+
+/// Returns `TrustedScriptURL` for the `url`
+pub fn script_url(url : &str) -> Result<JsValue> {
+    create("createScriptURL", url)
+}
+
+/// Returns `TrustedScript` for the `text`
+pub fn script(text : &str) -> Result<JsValue> {
+    create("createScript", text)
+}
+
+/// Returns `TrustedHTML` for the `html`
+pub fn html(html : &str) -> Result<JsValue> {
+    create("createHTML", html)
+}
+
+/// Assign the `src` property of a script (or worker related) element
+pub(crate) fn set_script_src(element : &Element, url : &str) -> Result<()> {
+    Reflect::set(element, &"src".into(), &script_url(url)?)?;
+    Ok(())
+}
+
+/// Assign the inline `text` of a script element
+pub(crate) fn set_script_text(element : &Element, text : &str) -> Result<()> {
+    Reflect::set(element, &"text".into(), &script(text)?)?;
+    Ok(())
+}
+
+/// Assign the `innerHTML` property of the element
+pub(crate) fn set_inner_html(element : &Element, text : &str) -> Result<()> {
+    Reflect::set(element, &"innerHTML".into(), &html(text)?)?;
+    Ok(())
+}
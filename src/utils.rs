@@ -1,5 +1,10 @@
 //! Helper functions for accessing DOM environment
+use std::future::Future;
+use js_sys::{Array, Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
 use web_sys::{Element, Window, Document};
+use workflow_core::channel::oneshot;
 
 /// Return the current browser [`web_sys::Window`] element
 pub fn window() -> Window{
@@ -11,9 +16,63 @@ pub fn document() -> Document{
     web_sys::window().unwrap().document().unwrap()
 }
 
+/// Return the `head` element of the `document`, falling back
+/// to the `body` element if the document has no `head`
+pub fn head_or_body(document : &Document) -> crate::result::Result<Element> {
+    let collection = document.get_elements_by_tag_name("head");
+    let element = if collection.length() > 0 {
+        collection.item(0)
+    } else {
+        document.get_elements_by_tag_name("body").item(0)
+    };
+    Ok(element.ok_or("Unable to locate head or body element")?)
+}
+
 /// Return the `body` element of the current document
 pub fn body()->std::result::Result<Element, String>{
     let b = document().query_selector("body").unwrap().ok_or("Unable to get body element".to_string())?;
     Ok(b)
 }
 
+
+/// Call `method` of the `target`
+pub(crate) fn call(target : &JsValue, method : &str, args : &[JsValue]) -> crate::result::Result<JsValue> {
+    let function = Reflect::get(target, &method.into())?;
+    Ok(Reflect::apply(function.unchecked_ref(), target, &args.iter().collect::<Array>())?)
+}
+
+/// Call `method` of the `target` and await the returned promise
+pub(crate) async fn call_async(target : &JsValue, method : &str, args : &[JsValue]) -> crate::result::Result<JsValue> {
+    let function = Reflect::get(target, &method.into())?;
+    let args = args.iter().collect::<Array>();
+    let promise = Reflect::apply(function.unchecked_ref(), target, &args)?;
+    Ok(JsFuture::from(promise.unchecked_into::<Promise>()).await?)
+}
+
+/// Returns the decoded body size of the resource loaded from `url`
+/// as reported by the Resource Timing API, or `0` if not available
+/// (e.g. for cross-origin resources without `Timing-Allow-Origin`)
+pub(crate) fn resource_size(url : &str) -> usize {
+    let entries = Reflect::get(&window(), &"performance".into())
+        .and_then(|performance| {
+            let get = Reflect::get(&performance, &"getEntriesByName".into())?;
+            Reflect::apply(get.unchecked_ref(), &performance, &Array::of1(&url.into()))
+        })
+        .map(|entries| Array::from(&entries))
+        .unwrap_or_default();
+    entries.iter()
+        .last()
+        .and_then(|entry| Reflect::get(&entry, &"decodedBodySize".into()).ok())
+        .and_then(|size| size.as_f64())
+        .unwrap_or_default() as usize
+}
+
+/// Spawn a JS `future` (which is not `Send`) on a local task,
+/// returning a `Send` future resolving with its result
+pub(crate) fn local<T : 'static>(future : impl Future<Output = crate::result::Result<T>> + 'static) -> impl Future<Output = crate::result::Result<T>> {
+    let (sender, receiver) = oneshot();
+    wasm_bindgen_futures::spawn_local(async move {
+        sender.try_send(future.await).ok();
+    });
+    async move { receiver.recv().await? }
+}
@@ -0,0 +1,268 @@
+//!
+//! [Web Workers](https://developer.mozilla.org/en-US/docs/Web/API/Worker) and
+//! [Shared Workers](https://developer.mozilla.org/en-US/docs/Web/API/SharedWorker)
+//! created from embedded scripts.
+//!
+//! Workers are started from blob URLs of embedded script buffers (see [`Worker::new`])
+//! or of declared loader content (see [`Context::worker`](crate::loader::Context::worker)),
+//! in which case a worker module can import other declared modules. Messages are
+//! exchanged via a typed [`WorkerChannel`].
+//!
+//! Example:
+//!
+//! ```rust
+//! let worker = Worker::new(PROCESSOR_JS, WorkerKind::Module)?;
+//! let channel = worker.channel::<String, f64>()?;
+//! channel.post(&"process".to_string())?;
+//! let result = channel.recv().await?;
+//! ```
+//!
+
+use std::marker::PhantomData;
+use js_sys::{Array, Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use workflow_core::channel::{unbounded, Receiver};
+use crate::blob::{create_blob_url, revoke_blob_url};
+use crate::error::Error;
+use crate::result::Result;
+use crate::trusted_types;
+use crate::utils::{window, call};
+
+/// Type of the worker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerKind {
+    /// Dedicated worker running a classic script
+    Classic,
+    /// Dedicated worker running a module
+    Module,
+    /// Shared worker running a classic script
+    SharedClassic,
+    /// Shared worker running a module
+    SharedModule,
+}
+
+impl WorkerKind {
+    pub fn is_shared(&self) -> bool {
+        matches!(self, WorkerKind::SharedClassic | WorkerKind::SharedModule)
+    }
+
+    pub fn is_module(&self) -> bool {
+        matches!(self, WorkerKind::Module | WorkerKind::SharedModule)
+    }
+}
+
+/// Conversion of messages exchanged with a worker
+pub trait Message : Sized {
+    fn to_js(&self) -> Result<JsValue>;
+    fn from_js(value : JsValue) -> Result<Self>;
+}
+
+impl Message for JsValue {
+    fn to_js(&self) -> Result<JsValue> {
+        Ok(self.clone())
+    }
+    fn from_js(value : JsValue) -> Result<Self> {
+        Ok(value)
+    }
+}
+
+impl Message for String {
+    fn to_js(&self) -> Result<JsValue> {
+        Ok(self.into())
+    }
+    fn from_js(value : JsValue) -> Result<Self> {
+        value.as_string().ok_or_else(|| Error::String(format!("worker message is not a string: {:?}", value)))
+    }
+}
+
+impl Message for f64 {
+    fn to_js(&self) -> Result<JsValue> {
+        Ok((*self).into())
+    }
+    fn from_js(value : JsValue) -> Result<Self> {
+        value.as_f64().ok_or_else(|| Error::String(format!("worker message is not a number: {:?}", value)))
+    }
+}
+
+impl Message for bool {
+    fn to_js(&self) -> Result<JsValue> {
+        Ok((*self).into())
+    }
+    fn from_js(value : JsValue) -> Result<Self> {
+        value.as_bool().ok_or_else(|| Error::String(format!("worker message is not a boolean: {:?}", value)))
+    }
+}
+
+impl Message for Vec<u8> {
+    fn to_js(&self) -> Result<JsValue> {
+        Ok(Uint8Array::from(self.as_slice()).into())
+    }
+    fn from_js(value : JsValue) -> Result<Self> {
+        match value.dyn_into::<Uint8Array>() {
+            Ok(array) => Ok(array.to_vec()),
+            Err(value) => Err(Error::String(format!("worker message is not a Uint8Array: {:?}", value)))
+        }
+    }
+}
+
+/// Dedicated or shared worker
+#[derive(Clone, Debug)]
+pub struct Worker {
+    kind : WorkerKind,
+    worker : Object,
+    /// the worker itself or the port of a shared worker
+    port : Object,
+    url : String,
+    /// the blob URL has been created by [`Worker::new`]
+    owned : bool,
+}
+
+unsafe impl Send for Worker {}
+unsafe impl Sync for Worker {}
+
+impl Worker {
+
+    /// Start a worker running the script `data` from a blob URL
+    pub fn new(data : &[u8], kind : WorkerKind) -> Result<Self> {
+        let url = create_blob_url(data, Some("application/javascript"))?;
+        let mut worker = Self::with_url(&url, kind, None)?;
+        worker.owned = true;
+        Ok(worker)
+    }
+
+    /// Start a worker running the script at `url`. The optional `name`
+    /// identifies a shared worker (workers with the same URL and name are shared).
+    pub fn with_url(url : &str, kind : WorkerKind, name : Option<&str>) -> Result<Self> {
+        let class = if kind.is_shared() { "SharedWorker" } else { "Worker" };
+        let class = Reflect::get(&window(), &class.into())?;
+        if class.is_undefined() {
+            return Err(format!("{:?} workers are not supported", kind).into());
+        }
+
+        let options = Object::new();
+        let type_ = if kind.is_module() { "module" } else { "classic" };
+        Reflect::set(&options, &"type".into(), &type_.into())?;
+        if let Some(name) = name {
+            Reflect::set(&options, &"name".into(), &name.into())?;
+        }
+
+        let args = Array::of2(&trusted_types::script_url(url)?, &options);
+        let worker : Object = Reflect::construct(class.unchecked_ref(), &args)?.unchecked_into();
+        let port : Object = if kind.is_shared() {
+            let port = Reflect::get(&worker, &"port".into())?;
+            call(&port, "start", &[])?;
+            port.unchecked_into()
+        } else {
+            worker.clone()
+        };
+
+        Ok(Worker { kind, worker, port, url : url.to_string(), owned : false })
+    }
+
+    pub fn kind(&self) -> WorkerKind {
+        self.kind
+    }
+
+    /// Underlying `Worker` or `SharedWorker` object
+    pub fn worker(&self) -> &Object {
+        &self.worker
+    }
+
+    /// URL of the worker script
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Post a raw `message` to the worker
+    pub fn post(&self, message : &JsValue) -> Result<()> {
+        call(&self.port, "postMessage", std::slice::from_ref(message))?;
+        Ok(())
+    }
+
+    /// Create a typed channel sending `Tx` messages to and
+    /// receiving `Rx` messages from the worker
+    pub fn channel<Tx : Message, Rx : Message + 'static>(&self) -> Result<WorkerChannel<Tx, Rx>> {
+        WorkerChannel::new(self)
+    }
+
+    /// Terminate a dedicated worker (or close the port of a shared worker).
+    /// The blob URL of a script started via [`Worker::new`] is revoked.
+    pub fn terminate(&self) -> Result<()> {
+        let method = if self.kind.is_shared() { "close" } else { "terminate" };
+        call(&self.port, method, &[])?;
+        if self.owned {
+            revoke_blob_url(&self.url)?;
+        }
+        Ok(())
+    }
+}
+
+/// Typed message channel to a [`Worker`]. Listeners are
+/// removed when the channel is dropped.
+pub struct WorkerChannel<Tx : Message, Rx : Message> {
+    worker : Worker,
+    receiver : Receiver<Result<Rx>>,
+    message : Closure<dyn FnMut(JsValue)>,
+    error : Closure<dyn FnMut(JsValue)>,
+    _tx : PhantomData<Tx>,
+}
+
+impl<Tx : Message, Rx : Message + 'static> WorkerChannel<Tx, Rx> {
+
+    fn new(worker : &Worker) -> Result<Self> {
+        let (sender, receiver) = unbounded();
+
+        let message_sender = sender.clone();
+        let message = Closure::wrap(Box::new(move |event : JsValue| {
+            let message = Reflect::get(&event, &"data".into())
+                .map_err(Error::from)
+                .and_then(Rx::from_js);
+            message_sender.try_send(message).ok();
+        }) as Box<dyn FnMut(JsValue)>);
+
+        let ident = worker.url.clone();
+        let error = Closure::wrap(Box::new(move |event : JsValue| {
+            sender.try_send(Err(Error::Load(ident.clone(), event))).ok();
+        }) as Box<dyn FnMut(JsValue)>);
+
+        listen(&worker.port, "addEventListener", "message", &message)?;
+        listen(&worker.port, "addEventListener", "messageerror", &error)?;
+        listen(&worker.worker, "addEventListener", "error", &error)?;
+
+        Ok(WorkerChannel { worker : worker.clone(), receiver, message, error, _tx : PhantomData })
+    }
+
+    pub fn worker(&self) -> &Worker {
+        &self.worker
+    }
+
+    /// Post a `message` to the worker
+    pub fn post(&self, message : &Tx) -> Result<()> {
+        self.worker.post(&message.to_js()?)
+    }
+
+    /// Receive the next message from the worker. Worker errors
+    /// and messages that can not be converted are returned as errors.
+    pub async fn recv(&self) -> Result<Rx> {
+        self.receiver.recv().await?
+    }
+
+    /// Returns the next message if one is pending
+    pub fn try_recv(&self) -> Option<Result<Rx>> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl<Tx : Message, Rx : Message> Drop for WorkerChannel<Tx, Rx> {
+    fn drop(&mut self) {
+        listen(&self.worker.port, "removeEventListener", "message", &self.message).ok();
+        listen(&self.worker.port, "removeEventListener", "messageerror", &self.error).ok();
+        listen(&self.worker.worker, "removeEventListener", "error", &self.error).ok();
+    }
+}
+
+fn listen(target : &Object, method : &str, event : &str, closure : &Closure<dyn FnMut(JsValue)>) -> Result<()> {
+    call(target, method, &[event.into(), closure.as_ref().clone()])?;
+    Ok(())
+}